@@ -1,4 +1,4 @@
-use std::{fs::File, io::BufReader, path::PathBuf};
+use std::path::PathBuf;
 
 use anyhow::Context;
 use clap::Parser;
@@ -14,14 +14,27 @@ struct Args {
 
     #[arg(long, help = "Displays headers", default_value = "false")]
     show_headers: bool,
+
+    #[arg(
+        long,
+        help = "Does not check the file headers against the ABI",
+        default_value = "false"
+    )]
+    skip_abi_check: bool,
+
+    #[arg(
+        long,
+        help = "The CRC of the ABI that wrote the file, checked against the ABI. Payload files carry no CRC, so it is only checked when given"
+    )]
+    crc: Option<u64>,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    let abi_file = File::open(&args.abi).context("unable to read abi file")?;
-    let abi_buf = BufReader::new(abi_file);
-    let abi = Abi::new(abi_buf, None)?;
+    // SAFETY: the CLI exits long before GreyCat could rewrite its ABI file
+    let abi =
+        unsafe { AbiBuilder::new().build_mapped(&args.abi) }.context("unable to read abi file")?;
 
     let value_buf = std::fs::read(&args.filepath).context("unable to read value file")?;
     let mut value_bytes = &value_buf[..];
@@ -30,12 +43,11 @@ fn main() -> anyhow::Result<()> {
     if args.show_headers {
         println!("{v_headers:#?}");
     }
-    if abi.headers.headers.protocol != v_headers.protocol {
-        anyhow::bail!(
-            "mismatched ABI protocol (got={}, expected={})",
-            v_headers.protocol,
-            abi.headers.headers.protocol
-        );
+    if !args.skip_abi_check {
+        abi.check_headers(&v_headers)?;
+        if let Some(crc) = args.crc {
+            abi.check_crc(crc)?;
+        }
     }
     let mut bytes = value_bytes;
     loop {
@@ -44,7 +56,7 @@ fn main() -> anyhow::Result<()> {
         }
 
         let value = bytes.read_value(&abi)?;
-        println!("{value:#?}");
+        println!("{:#}", value.display(&abi));
     }
 
     Ok(())
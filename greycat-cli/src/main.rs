@@ -1,9 +1,92 @@
-use std::{collections::HashMap, fs::File, time::Instant};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
+use anyhow::Context;
+use clap::{Parser, Subcommand};
 use greycat_sdk::prelude::*;
 use reqwest::blocking::*;
 
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the changes between two ABI files, fails if any of them is breaking
+    Diff {
+        #[arg(help = "The path to the old ABI")]
+        old: PathBuf,
+
+        #[arg(help = "The path to the new ABI")]
+        new: PathBuf,
+    },
+    /// Writes the OpenAPI document of the functions of an ABI file
+    Openapi {
+        #[arg(help = "The path to the ABI")]
+        abi: PathBuf,
+
+        #[arg(short, long, help = "The path of the document, stdout if not set")]
+        output: Option<PathBuf>,
+    },
+}
+
 fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Diff { old, new }) => run_diff(&old, &new),
+        Some(Command::Openapi { abi, output }) => run_openapi(&abi, output.as_deref()),
+        None => run_anything(),
+    }
+}
+
+fn read_abi(path: &Path) -> anyhow::Result<Abi> {
+    // SAFETY: the CLI exits long before GreyCat could rewrite its ABI file
+    unsafe { AbiBuilder::new().build_mapped(path) }
+        .with_context(|| format!("unable to read abi file {}", path.display()))
+}
+
+fn run_diff(old: &Path, new: &Path) -> anyhow::Result<()> {
+    let old = read_abi(old)?;
+    let new = read_abi(new)?;
+
+    let diff = greycat_sdk::abi::diff(&old, &new);
+    for change in &diff.changes {
+        println!("{change}");
+    }
+
+    let nb_breaking = diff.breaking().count();
+    if nb_breaking > 0 {
+        anyhow::bail!("{nb_breaking} breaking change(s)");
+    }
+
+    Ok(())
+}
+
+fn run_openapi(abi: &Path, output: Option<&Path>) -> anyhow::Result<()> {
+    let abi = read_abi(abi)?;
+    let document = abi.openapi();
+
+    match output {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("unable to create {}", path.display()))?;
+            serde_json::to_writer_pretty(file, &document)?;
+        }
+        None => {
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &document)?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn run_anything() -> anyhow::Result<()> {
     let client = Client::new();
 
     let bytes = client
@@ -14,7 +97,7 @@ fn main() -> anyhow::Result<()> {
 
     let abi = AbiBuilder::new().build(&*bytes)?;
 
-    serde_json::to_writer(File::create("abi.json")?, &abi)?;
+    serde_json::to_writer(File::create("abi.json")?, &abi.named())?;
 
     let args = Value::Array(vec![
         Value::Int(42),
@@ -61,11 +144,11 @@ fn main() -> anyhow::Result<()> {
         )),
     ]);
 
-    let mut body: Vec<u8> = Vec::new();
-    let mut n = abi.headers.write_to(&mut body, &abi)?;
-    n += args.write_to(&mut body, &abi)?;
-    eprintln!("payload of {n} bytes");
-    assert_eq!(body.len(), n);
+    let anything = abi
+        .get_fn_by_fqn("project::anything")
+        .expect("test project.gcl is expected to define a function 'project::anything'");
+    let body = anything.encode_request(std::slice::from_ref(&args), &abi)?;
+    eprintln!("payload of {} bytes", body.len());
 
     let res = client
         .post("http://localhost:8080/project::anything")
@@ -77,7 +160,8 @@ fn main() -> anyhow::Result<()> {
     let bytes = res.bytes()?;
     let mut bytes = &bytes[..];
 
-    let _ = bytes.read_request_headers()?;
+    let headers = bytes.read_request_headers()?;
+    abi.check_headers(&headers)?;
     let result = bytes.read_value(&abi)?;
     assert!(bytes.is_empty());
     assert_eq!(args, result);
@@ -106,7 +190,7 @@ fn main() -> anyhow::Result<()> {
 //         types: types.clone(),
 //         functions: functions.clone(),
 //     };
-//     serde_json::to_writer(File::create("abi.json")?, &abi)?;
+//     serde_json::to_writer(File::create("abi.json")?, &abi.named())?;
 
 //     // _run_read_endpoints(&client, &abi)?;
 //     let value = _run(&client, &abi, "project::loadProblem")?;
@@ -120,7 +204,8 @@ fn _run_records() -> anyhow::Result<()> {
     let abi = Abi::new(&*bytes, None)?;
 
     let mut bytes = &std::fs::read("gcdata/files/records.gcb").unwrap()[..];
-    let _headers = bytes.read_request_headers()?;
+    let headers = bytes.read_request_headers()?;
+    abi.check_headers(&headers)?;
     let start = Instant::now();
     loop {
         if bytes.is_empty() {
@@ -136,15 +221,12 @@ fn _run_records() -> anyhow::Result<()> {
 fn _run_read_endpoints(client: &Client, abi: &Abi) -> anyhow::Result<()> {
     let mut requests = HashMap::new();
 
-    let read_id = abi.get_symbol_id("read").unwrap_or(0);
-
-    for f in &*abi.functions {
-        if f.module != read_id {
-            continue;
+    if let Some(read) = abi.module("read") {
+        for f in &read.functions {
+            let fqn = f.function.named_fqn(abi);
+            let value = _run(client, abi, &fqn)?;
+            requests.insert(fqn, serde_json::to_value(value.to_json(abi))?);
         }
-        let fqn = f.fqn();
-        let value = _run(client, abi, &fqn)?;
-        requests.insert(fqn, value);
     }
 
     serde_json::to_writer_pretty(std::io::stdout(), &requests)?;
@@ -160,7 +242,8 @@ fn _run<'abi>(client: &Client, abi: &'abi Abi, method: &str) -> anyhow::Result<V
         .bytes()?;
     let mut bytes = &bytes[..];
 
-    let _headers = bytes.read_request_headers()?;
+    let headers = bytes.read_request_headers()?;
+    abi.check_headers(&headers)?;
     let value = bytes.read_value(abi)?;
     assert!(bytes.is_empty());
 
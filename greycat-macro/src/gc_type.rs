@@ -0,0 +1,185 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Ident, LitStr, Result, Type};
+
+/// `#[gc(type = "project::Person")]` on the type, `#[gc(name = "firstName")]` on fields and variants
+fn gc_attr(attrs: &[syn::Attribute], key: &str) -> Result<Option<LitStr>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("gc")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(key) {
+                value = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error(format!("expected `{key} = \"...\"`")))
+            }
+        })?;
+    }
+    Ok(value)
+}
+
+/// The GreyCat name of a field or variant, its Rust name unless renamed
+fn gc_name(attrs: &[syn::Attribute], ident: &Ident) -> Result<LitStr> {
+    Ok(gc_attr(attrs, "name")?.unwrap_or_else(|| {
+        let name = ident.to_string();
+        let name = name.trim_start_matches("r#");
+        LitStr::new(name, ident.span())
+    }))
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+pub(crate) fn derive(ast: &DeriveInput) -> Result<TokenStream> {
+    let name = &ast.ident;
+    if !ast.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &ast.generics,
+            "`#[derive(GcType)]` does not support generic types",
+        ));
+    }
+    let fqn = gc_attr(&ast.attrs, "type")?.ok_or_else(|| {
+        Error::new_spanned(
+            name,
+            "`#[derive(GcType)]` requires `#[gc(type = \"module::Type\")]`",
+        )
+    })?;
+
+    let (to_value, from_value) = match &ast.data {
+        Data::Struct(data) => {
+            let Fields::Named(fields) = &data.fields else {
+                return Err(Error::new_spanned(
+                    name,
+                    "`#[derive(GcType)]` only supports structs with named fields",
+                ));
+            };
+            let mut idents = Vec::new();
+            let mut attrs = Vec::new();
+            let mut nullables = Vec::new();
+            for field in &fields.named {
+                let ident = field.ident.as_ref().unwrap();
+                attrs.push(gc_name(&field.attrs, ident)?);
+                nullables.push(is_option(&field.ty));
+                idents.push(ident);
+            }
+            let check = quote! {
+                ::greycat_sdk::gc_type::check_fields(abi, Self::TYPE, &[#( (#attrs, #nullables) ),*])?;
+            };
+            (
+                quote! {
+                    #check
+                    ::greycat_sdk::gc_type::object(abi, Self::TYPE, ::std::vec![
+                        #( (#attrs, ::greycat_sdk::gc_type::GcType::to_value(&self.#idents, abi)?) ),*
+                    ])
+                },
+                quote! {
+                    #check
+                    let obj = ::greycat_sdk::gc_type::as_object(value, Self::TYPE, abi)?;
+                    ::std::result::Result::Ok(Self {
+                        #( #idents: ::greycat_sdk::gc_type::GcType::from_value(obj.get(#attrs, abi)?, abi)? ),*
+                    })
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let mut idents = Vec::new();
+            let mut keys = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(Error::new_spanned(
+                        variant,
+                        "`#[derive(GcType)]` only supports unit variants",
+                    ));
+                }
+                keys.push(gc_name(&variant.attrs, &variant.ident)?);
+                idents.push(&variant.ident);
+            }
+            if idents.is_empty() {
+                let to_value = quote! {
+                    let _ = abi;
+                    match *self {}
+                };
+                let from_value = quote! {
+                    let key = ::greycat_sdk::gc_type::enum_key(value, Self::TYPE, abi)?;
+                    ::std::result::Result::Err(::greycat_sdk::Error::UnknownEnumKey {
+                        ty: Self::TYPE.to_string(),
+                        key: key.to_string(),
+                    })
+                };
+                (to_value, from_value)
+            } else {
+                (
+                    quote! {
+                        let key = match *self {
+                            #( Self::#idents => #keys, )*
+                        };
+                        ::greycat_sdk::gc_type::enum_value(abi, Self::TYPE, key)
+                    },
+                    quote! {
+                        match ::greycat_sdk::gc_type::enum_key(value, Self::TYPE, abi)? {
+                            #( #keys => ::std::result::Result::Ok(Self::#idents), )*
+                            key => ::std::result::Result::Err(::greycat_sdk::Error::UnknownEnumKey {
+                                ty: Self::TYPE.to_string(),
+                                key: key.to_string(),
+                            }),
+                        }
+                    },
+                )
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                name,
+                "`#[derive(GcType)]` does not support unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl<'abi> ::greycat_sdk::gc_type::GcType<'abi> for #name {
+            const TYPE: &'static str = #fqn;
+
+            fn to_value(
+                &self,
+                abi: &'abi ::greycat_sdk::abi::Abi,
+            ) -> ::greycat_sdk::Result<::greycat_sdk::value::Value<'abi>> {
+                #to_value
+            }
+
+            fn from_value(
+                value: &::greycat_sdk::value::Value<'abi>,
+                abi: &'abi ::greycat_sdk::abi::Abi,
+            ) -> ::greycat_sdk::Result<Self> {
+                #from_value
+            }
+        }
+
+        impl ::greycat_sdk::serialize::AbiSerialize for #name {
+            fn write_to<W: ::std::io::Write>(
+                &self,
+                writer: &mut W,
+                abi: &::greycat_sdk::abi::Abi,
+            ) -> ::greycat_sdk::Result<usize> {
+                let value = ::greycat_sdk::gc_type::GcType::to_value(self, abi)?;
+                ::greycat_sdk::serialize::AbiSerialize::write_to(&value, writer, abi)
+            }
+
+            fn write_raw_to<W: ::std::io::Write>(
+                &self,
+                writer: &mut W,
+                abi: &::greycat_sdk::abi::Abi,
+            ) -> ::greycat_sdk::Result<usize> {
+                let value = ::greycat_sdk::gc_type::GcType::to_value(self, abi)?;
+                ::greycat_sdk::serialize::AbiSerialize::write_raw_to(&value, writer, abi)
+            }
+        }
+    })
+}
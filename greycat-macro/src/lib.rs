@@ -2,12 +2,38 @@ use proc_macro::TokenStream;
 use quote::quote;
 use syn::{parse_macro_input, Data, DataStruct, DeriveInput, Fields};
 
+mod gc_type;
+
 #[proc_macro_derive(Type)]
 pub fn derive_type(input: TokenStream) -> TokenStream {
     let ast = syn::parse(input).unwrap();
     impl_greycat_type_trait(&ast)
 }
 
+/// Implements `GcType` and `AbiSerialize` of `greycat-sdk` for a struct with named fields, or an
+/// enum with unit variants, bound to the GreyCat type given by `#[gc(type = "module::Type")]`.
+///
+/// Fields and variants are the attributes and variants of the same name, unless renamed with
+/// `#[gc(name = "...")]`. `Option` fields are nullable attributes. The fields are checked against
+/// the attributes of the type of the ABI on every conversion.
+///
+/// ```ignore
+/// #[derive(GcType)]
+/// #[gc(type = "project::Person")]
+/// struct Person {
+///     name: String,
+///     #[gc(name = "birthDate")]
+///     birth_date: Option<Time>,
+/// }
+/// ```
+#[proc_macro_derive(GcType, attributes(gc))]
+pub fn derive_gc_type(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    gc_type::derive(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
 #[proc_macro_attribute]
 pub fn greycat_object(_args: TokenStream, input: TokenStream) -> TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
@@ -1,20 +1,36 @@
-use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
 use std::collections::BTreeMap;
+use std::hash::BuildHasher;
 use std::io::Write;
-use std::rc::Rc;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
 use std::{collections::HashMap, io::Read};
 
-use anyhow::{anyhow, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use hashbrown::HashTable;
 use serde::ser::SerializeSeq;
 use serde::Serialize;
 
+use crate::error::{Error, Result, Tracked};
 use crate::library::Library;
-use crate::prelude::{TypeFactory, TypeLoader};
+use crate::prelude::TypeLoader;
 use crate::serialize::AbiSerialize;
 use crate::std::StdLibrary;
 use crate::varint::VarintRead;
 
+mod call;
+mod diff;
+pub mod json;
+mod mapped;
+mod modules;
+pub mod openapi;
+pub mod schema;
+
+pub use diff::{diff, AbiChange, AbiChangeKind, AbiDiff, Compatibility};
+pub use json::NamedAbi;
+pub use mapped::MappedAbi;
+pub use modules::{AbiModule, ModuleFn, ModuleType, ModuleVar};
+
 #[derive(Default)]
 pub struct AbiBuilder {
     libraries: Option<Vec<Box<dyn Library>>>,
@@ -51,6 +67,23 @@ impl AbiBuilder {
     {
         Abi::new(bytes, self.libraries)
     }
+
+    /// Builds the `Abi` from a memory-mapped ABI file, eg. `gcdata/store/abi`
+    ///
+    /// The symbols are borrowed from the map and the lookup tables are built on first use, but
+    /// the types and functions are decoded upfront: use [`MappedAbi`] to only decode them when
+    /// needed.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified nor truncated while the `Abi` is alive, see [`MappedAbi::open`].
+    pub unsafe fn build_mapped<P>(self, path: P) -> Result<Abi>
+    where
+        P: AsRef<Path>,
+    {
+        // SAFETY: upheld by the caller
+        unsafe { MappedAbi::open(path) }?.into_abi(self.libraries)
+    }
 }
 
 #[derive(Serialize)]
@@ -65,17 +98,167 @@ pub struct Abi {
     pub functions: AbiFunctions,
     #[serde(skip)]
     pub libraries: Vec<Box<dyn Library>>,
+    /// the loaders configured by each library, by library name then fqn
     #[serde(skip)]
-    pub loaders: BTreeMap<&'static str, Box<dyn TypeLoader>>,
+    loaders: BTreeMap<&'static str, BTreeMap<&'static str, Box<dyn TypeLoader>>>,
     #[serde(skip)]
-    pub factories: BTreeMap<&'static str, Box<dyn TypeFactory>>,
+    index: AbiIndex,
+}
+
+/// Lookup tables of an `Abi`, each built on first use
+#[derive(Debug, Default)]
+struct AbiIndex {
+    types: OnceLock<TypesIndex>,
+    functions: OnceLock<FunctionsIndex>,
+    /// attribute offset by name symbol, per type offset, built the first time the type is looked up
+    attrs: Box<[OnceLock<HashMap<u32, usize>>]>,
+    /// `(<library>, <fqn>)` of the loader, by native type offset
+    loaders: HashMap<u32, (&'static str, &'static str)>,
+}
+
+impl AbiIndex {
+    fn new(types: &AbiTypes) -> Self {
+        Self {
+            attrs: (0..types.len()).map(|_| OnceLock::new()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn types(&self, symbols: &AbiSymbols, types: &AbiTypes) -> &TypesIndex {
+        self.types.get_or_init(|| TypesIndex::new(symbols, types))
+    }
+
+    fn functions(&self, symbols: &AbiSymbols, functions: &AbiFunctions) -> &FunctionsIndex {
+        self.functions
+            .get_or_init(|| FunctionsIndex::new(symbols, functions))
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TypesIndex {
+    /// type offset by `(<module>, <name>)` symbols
+    by_name: HashMap<(u32, u32), u32>,
+    /// type offset by `"<module>::<name>"`
+    by_fqn: HashMap<String, u32>,
+}
+
+impl TypesIndex {
+    pub(crate) fn new(symbols: &AbiSymbols, types: &AbiTypes) -> Self {
+        let mut index = Self {
+            by_name: HashMap::with_capacity(types.len()),
+            by_fqn: HashMap::with_capacity(types.len()),
+        };
+        // when names collide, the first definition wins, just like a linear scan would
+        for ty in types.iter() {
+            index
+                .by_name
+                .entry((ty.module, ty.name))
+                .or_insert(ty.offset);
+            index
+                .by_fqn
+                .entry(format!("{}::{}", &symbols[ty.module], &symbols[ty.name]))
+                .or_insert(ty.offset);
+        }
+        index
+    }
+
+    /// Returns the offset of the type named `"<module>::<name>"`
+    pub(crate) fn get(&self, fqn: &str) -> Option<u32> {
+        self.by_fqn.get(fqn).copied()
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct FunctionsIndex {
+    /// function offset by `(<module>, <type>?, <name>)` symbols
+    by_name: HashMap<(u32, Option<u32>, u32), u32>,
+    /// function offset by `"<module>::<name>"` or `"<module>::<type>::<name>"`
+    by_fqn: HashMap<String, u32>,
+}
+
+impl FunctionsIndex {
+    pub(crate) fn new(symbols: &AbiSymbols, functions: &AbiFunctions) -> Self {
+        let mut index = Self {
+            by_name: HashMap::with_capacity(functions.len()),
+            by_fqn: HashMap::with_capacity(functions.len()),
+        };
+        for (offset, function) in functions.iter().enumerate() {
+            let offset = offset as u32;
+            index
+                .by_name
+                .entry((function.module, function.r#type, function.name))
+                .or_insert(offset);
+            index
+                .by_fqn
+                .entry(function.named_fqn_with(symbols))
+                .or_insert(offset);
+        }
+        index
+    }
+
+    /// Returns the offset of the function named `"<module>::<name>"` or, for type methods,
+    /// `"<module>::<type>::<name>"`
+    pub(crate) fn get(&self, fqn: &str) -> Option<u32> {
+        self.by_fqn.get(fqn).copied()
+    }
 }
 
 impl Abi {
-    pub fn new<T>(mut bytes: T, libraries: Option<Vec<Box<dyn Library>>>) -> Result<Self>
+    pub fn new<T>(bytes: T, libraries: Option<Vec<Box<dyn Library>>>) -> Result<Self>
     where
         T: Read,
     {
+        let (headers, symbols, types, functions) = Tracked::run(bytes, |bytes| {
+            let headers = bytes.read_abi_headers()?;
+            let symbols = bytes.read_abi_symbols()?;
+            let types = bytes.read_abi_types(&symbols)?;
+            let functions = bytes.read_abi_functions(&symbols, &types)?;
+            Ok((headers, symbols, types, functions))
+        })?;
+
+        Self::from_parts(headers, symbols, types, functions, libraries)
+    }
+
+    /// Creates an `Abi` from already decoded parts.
+    ///
+    /// This is mostly useful to build synthetic ABIs (eg. for tests) that can then be written
+    /// back to the binary ABI format using [`AbiSerialize`].
+    pub fn from_parts(
+        headers: AbiHeaders,
+        symbols: AbiSymbols,
+        types: AbiTypes,
+        functions: AbiFunctions,
+        libraries: Option<Vec<Box<dyn Library>>>,
+    ) -> Result<Self> {
+        let index = AbiIndex::new(&types);
+        Self::from_indexed_parts(headers, symbols, types, functions, index, libraries)
+    }
+
+    /// Same as [`Abi::from_parts`], keeping the lookup tables already built by a [`MappedAbi`]
+    pub(crate) fn from_mapped_parts(
+        headers: AbiHeaders,
+        symbols: AbiSymbols,
+        types: AbiTypes,
+        functions: AbiFunctions,
+        (types_index, functions_index): (OnceLock<TypesIndex>, OnceLock<FunctionsIndex>),
+        libraries: Option<Vec<Box<dyn Library>>>,
+    ) -> Result<Self> {
+        let index = AbiIndex {
+            types: types_index,
+            functions: functions_index,
+            ..AbiIndex::new(&types)
+        };
+        Self::from_indexed_parts(headers, symbols, types, functions, index, libraries)
+    }
+
+    fn from_indexed_parts(
+        headers: AbiHeaders,
+        symbols: AbiSymbols,
+        types: AbiTypes,
+        functions: AbiFunctions,
+        mut index: AbiIndex,
+        libraries: Option<Vec<Box<dyn Library>>>,
+    ) -> Result<Self> {
         let libraries = match libraries {
             Some(mut libraries) => {
                 let has_std = libraries
@@ -96,58 +279,106 @@ impl Abi {
             }
         };
 
-        let headers = bytes.read_abi_headers()?;
-        let symbols = bytes.read_abi_symbols()?;
-        let types = bytes.read_abi_types(&symbols)?;
-        let functions = bytes.read_abi_functions(&symbols, &types)?;
+        let mut loaders = BTreeMap::new();
+        for library in &libraries {
+            let mut lib_loaders = BTreeMap::new();
+            library.configure(&mut lib_loaders)?;
+            loaders.insert(library.name(), lib_loaders);
+        }
 
-        Ok(Self {
+        for ty in types.iter().filter(|ty| ty.is_native) {
+            let lib_loaders =
+                loaders
+                    .get_key_value(&symbols[ty.lib_name])
+                    .and_then(|(lib, lib_loaders)| {
+                        let fqn = format!("{}::{}", &symbols[ty.module], &symbols[ty.name]);
+                        let (fqn, _) = lib_loaders.get_key_value(fqn.as_str())?;
+                        Some((*lib, *fqn))
+                    });
+            if let Some(key) = lib_loaders {
+                index.loaders.insert(ty.offset, key);
+            }
+        }
+
+        let mut abi = Self {
             headers,
             symbols,
             types,
             functions,
-            libraries,
-            loaders: Default::default(),
-            factories: Default::default(),
-        })
+            libraries: Vec::new(),
+            loaders,
+            index,
+        };
+
+        // libraries are initialized once the `Abi` is complete
+        let mut libraries = libraries;
+        for library in &mut libraries {
+            library.init(&abi)?;
+        }
+        abi.libraries = libraries;
+
+        Ok(abi)
+    }
+
+    /// Returns the loader registered for the native type `ty` by the library named after its `lib_name`
+    pub fn get_loader(&self, ty: &AbiType) -> Option<&dyn TypeLoader> {
+        let (lib, fqn) = self.index.loaders.get(&ty.offset)?;
+        self.loaders.get(lib)?.get(fqn).map(Box::as_ref)
     }
 
     pub fn get_symbol_id(&self, value: &str) -> Option<u32> {
-        self.symbols.id_by_name.get(value).copied()
+        self.symbols.get(value)
     }
 
-    pub fn get_symbol_by_id(&self, id: u32) -> AbiSymbol {
+    pub fn get_symbol_by_id(&self, id: u32) -> AbiSymbol<'_> {
         AbiSymbol(&self.symbols[id])
     }
 
-    pub fn get_symbol(&self, str: &str) -> Option<AbiSymbol> {
-        self.symbols
-            .id_by_name
-            .get(str)
-            .map(|id| self.get_symbol_by_id(*id))
+    pub fn get_symbol(&self, str: &str) -> Option<AbiSymbol<'_>> {
+        self.symbols.get(str).map(|id| self.get_symbol_by_id(id))
     }
 
-    pub fn get_type_by_fqn(&self, fqn: &str) -> Option<Rc<AbiType>> {
-        let (module, name) = self.parse_fqn(fqn)?;
-        self.types
-            .iter()
-            .find(|t| t.module == module && t.name == name)
-            .cloned()
+    /// Returns the type named `"<module>::<name>"`
+    pub fn get_type_by_fqn(&self, fqn: &str) -> Option<Arc<AbiType>> {
+        let offset = self.index.types(&self.symbols, &self.types).get(fqn)?;
+        self.types.get(offset)
     }
 
-    pub fn get_type_by_module_and_name(&self, module: &str, name: &str) -> Option<Rc<AbiType>> {
-        let (module, name) = self.module_and_name(module, name)?;
-        self.types
-            .iter()
-            .find(|t| t.module == module && t.name == name)
-            .cloned()
+    pub fn get_type_by_module_and_name(&self, module: &str, name: &str) -> Option<Arc<AbiType>> {
+        let key = self.module_and_name(module, name)?;
+        let index = self.index.types(&self.symbols, &self.types);
+        let offset = index.by_name.get(&key)?;
+        self.types.get(*offset)
     }
 
+    /// Returns the function named `"<module>::<name>"` or, for type methods, `"<module>::<type>::<name>"`
     pub fn get_fn_by_fqn(&self, fqn: &str) -> Option<&AbiFn> {
-        let (module, name) = self.parse_fqn(fqn)?;
-        self.functions
-            .iter()
-            .find(|f| f.module == module && f.name == name)
+        let offset = self.get_fn_offset_by_fqn(fqn)?;
+        self.functions.get(offset as usize)
+    }
+
+    /// Returns the offset of the function named `"<module>::<name>"` or, for type methods, `"<module>::<type>::<name>"`
+    pub fn get_fn_offset_by_fqn(&self, fqn: &str) -> Option<u32> {
+        self.index
+            .functions(&self.symbols, &self.functions)
+            .get(fqn)
+    }
+
+    /// Returns the function `name` of the given `module`, or the method `name` of type `ty` if given
+    pub fn get_fn_by_module_type_and_name(
+        &self,
+        module: &str,
+        ty: Option<&str>,
+        name: &str,
+    ) -> Option<&AbiFn> {
+        let (module, name) = self.module_and_name(module, name)?;
+        let ty = match ty {
+            Some(ty) => Some(self.symbols.get(ty)?),
+            None => None,
+        };
+        let index = self.index.functions(&self.symbols, &self.functions);
+        let offset = index.by_name.get(&(module, ty, name))?;
+        self.functions.get(*offset as usize)
     }
 
     pub fn get_modvars(&self) -> Vec<ModVar> {
@@ -161,9 +392,9 @@ impl Abi {
                         .expect("module vars are supposed to be named '<module>.<name>'");
 
                     modvars.push(ModVar {
-                        module: self.symbols.id_by_name.get(module).copied().unwrap(),
-                        name: self.symbols.id_by_name.get(name).copied().unwrap(),
-                        ty: attr.prog_type_offset.clone().into_inner(),
+                        module: self.symbols.get(module).unwrap(),
+                        name: self.symbols.get(name).unwrap(),
+                        ty: attr.prog_type_offset,
                         nullable: attr.nullable,
                     });
                 }
@@ -173,24 +404,79 @@ impl Abi {
         modvars
     }
 
-    /// Splits a `"<module>::<name>"` fqn into the corresponding tuple `(<module:u32>, <name:u32>)`
-    fn parse_fqn(&self, fqn: &str) -> Option<(u32, u32)> {
-        let (module, name) = fqn.split_once("::")?;
-        self.module_and_name(module, name)
+    /// Checks that the given payload `headers` were written using this ABI.
+    ///
+    /// Decoding a payload against a stale ABI does not necessarily fail, it can silently
+    /// produce wrong values, hence this check.
+    pub fn check_headers(&self, headers: &RequestHeaders) -> std::result::Result<(), AbiMismatch> {
+        let expected = &self.headers.headers;
+        if headers.protocol != expected.protocol {
+            return Err(AbiMismatch::Protocol {
+                expected: expected.protocol,
+                got: headers.protocol,
+            });
+        }
+        if headers.magic != expected.magic {
+            return Err(AbiMismatch::Magic {
+                expected: expected.magic,
+                got: headers.magic,
+            });
+        }
+        if headers.version != expected.version {
+            return Err(AbiMismatch::Version {
+                expected: expected.version,
+                got: headers.version,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks that the given `crc` matches the CRC of this ABI
+    pub fn check_crc(&self, crc: u64) -> std::result::Result<(), AbiMismatch> {
+        if crc != self.headers.crc {
+            return Err(AbiMismatch::Crc {
+                expected: self.headers.crc,
+                got: crc,
+            });
+        }
+        Ok(())
+    }
+
+    /// Checks both the request headers and the CRC of the given ABI `headers`,
+    /// eg. the headers of the ABI currently served by a GreyCat instance.
+    pub fn check_abi_headers(&self, headers: &AbiHeaders) -> std::result::Result<(), AbiMismatch> {
+        self.check_headers(&headers.headers)?;
+        self.check_crc(headers.crc)
     }
 
     /// Returns the corresponding tuple `(<module:u32>, <name:u32>)`
     fn module_and_name(&self, module: &str, name: &str) -> Option<(u32, u32)> {
-        let module = self.symbols.id_by_name.get(module);
-        let name = self.symbols.id_by_name.get(name);
+        let module = self.symbols.get(module);
+        let name = self.symbols.get(name);
         if let (Some(module), Some(name)) = (module, name) {
-            Some((*module, *name))
+            Some((module, name))
         } else {
             None
         }
     }
 }
 
+impl AbiSerialize for Abi {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // an ABI has no headers that precedes it
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the ABI using the same layout as `gcdata/store/abi`
+    fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        let mut n = self.headers.write_raw_to(writer, abi)?;
+        n += self.symbols.write_raw_to(writer, abi)?;
+        n += self.types.write_raw_to(writer, abi)?;
+        n += self.functions.write_raw_to(writer, abi)?;
+        Ok(n)
+    }
+}
+
 impl std::fmt::Debug for Abi {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Abi")
@@ -202,7 +488,7 @@ impl std::fmt::Debug for Abi {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct AbiHeaders {
     pub headers: RequestHeaders,
     pub crc: u64,
@@ -221,21 +507,71 @@ impl AbiSerialize for AbiHeaders {
     }
 
     #[inline]
-    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
-        writer.write_u16::<LE>(self.headers.protocol)?;
-        writer.write_u16::<LE>(self.headers.magic)?;
-        writer.write_u32::<LE>(self.headers.version)?;
-        Ok(8) // 2 + 2 + 4
+    fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        let n = self.headers.write_raw_to(writer, abi)?;
+        writer.write_u64::<LE>(self.crc)?;
+        Ok(n + 8)
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct RequestHeaders {
     pub protocol: u16,
     pub magic: u16,
     pub version: u32,
 }
 
+impl AbiSerialize for RequestHeaders {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // RequestHeaders have no headers that precedes them
+        self.write_raw_to(writer, abi)
+    }
+
+    #[inline]
+    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
+        writer.write_u16::<LE>(self.protocol)?;
+        writer.write_u16::<LE>(self.magic)?;
+        writer.write_u32::<LE>(self.version)?;
+        Ok(8) // 2 + 2 + 4
+    }
+}
+
+/// The field that differs between a payload (or another ABI) and the loaded ABI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbiMismatch {
+    Protocol { expected: u16, got: u16 },
+    Magic { expected: u16, got: u16 },
+    Version { expected: u32, got: u32 },
+    Crc { expected: u64, got: u64 },
+}
+
+impl std::fmt::Display for AbiMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AbiMismatch::Protocol { expected, got } => {
+                write!(
+                    f,
+                    "mismatched ABI protocol (got={got}, expected={expected})"
+                )
+            }
+            AbiMismatch::Magic { expected, got } => {
+                write!(f, "mismatched ABI magic (got={got}, expected={expected})")
+            }
+            AbiMismatch::Version { expected, got } => {
+                write!(f, "mismatched ABI version (got={got}, expected={expected})")
+            }
+            AbiMismatch::Crc { expected, got } => {
+                write!(
+                    f,
+                    "mismatched ABI crc (got={got:#x}, expected={expected:#x})"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for AbiMismatch {}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 pub struct AbiSymbol<'abi>(pub &'abi str);
 
@@ -253,7 +589,9 @@ impl AbiSerialize for AbiSymbol<'_> {
 
         let id = abi
             .get_symbol_id(self.0)
-            .ok_or_else(|| anyhow!("unknown symbol '{}'", self.0))?;
+            .ok_or_else(|| Error::UnknownSymbol {
+                symbol: self.0.to_string(),
+            })?;
 
         let n = writer.write_vu32((id << 1) | 1)?;
         Ok(n)
@@ -266,10 +604,21 @@ impl std::fmt::Display for AbiSymbol<'_> {
     }
 }
 
-#[derive(Debug)]
+/// The bytes the symbols are borrowed from, either owned or memory-mapped
+pub(crate) type SymbolsData = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
+/// The symbols of an ABI.
+///
+/// The symbols are not allocated one by one, they are all borrowed from a single buffer
+/// (eg. the bytes of a memory-mapped ABI file, see [`MappedAbi`]) that is shared between clones.
+#[derive(Clone)]
 pub struct AbiSymbols {
-    pub symbols: Box<[Box<str>]>,
-    id_by_name: HashMap<&'static str, u32>,
+    data: SymbolsData,
+    /// `(start, end)` of each symbol in `data`, they are all checked to be valid UTF-8 on creation
+    spans: Box<[(usize, usize)]>,
+    /// symbol ids hashed by the symbol they refer to
+    ids: HashTable<u32>,
+    hasher: RandomState,
 }
 
 impl serde::Serialize for AbiSymbols {
@@ -277,21 +626,133 @@ impl serde::Serialize for AbiSymbols {
     where
         S: serde::Serializer,
     {
-        let mut seq = serializer.serialize_seq(Some(self.symbols.len()))?;
-        for symb in self.symbols.iter() {
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for symb in self.iter() {
             seq.serialize_element(symb)?;
         }
         seq.end()
     }
 }
 
+impl std::fmt::Debug for AbiSymbols {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
 impl AbiSymbols {
-    pub fn from_bytes<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+    /// Creates the symbol table from the given symbols.
+    ///
+    /// The empty symbol is implicitly added at offset `0`, so the first given symbol gets the id `1`.
+    pub fn new<I, S>(symbols: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut data = Vec::new();
+        let mut spans = vec![(0, 0)];
+        for symbol in symbols {
+            let start = data.len();
+            data.extend_from_slice(symbol.as_ref().as_bytes());
+            spans.push((start, data.len()));
+        }
+        Self::from_spans(Arc::new(data), spans).expect("symbols are valid UTF-8")
+    }
+
+    /// Reads the symbols section that starts at `offset` in `data`, without copying the symbols.
+    ///
+    /// Returns the symbols along with the offset of the end of the section.
+    pub(crate) fn from_shared(data: SymbolsData, offset: usize) -> Result<(Self, usize)> {
+        let (spans, end) = {
+            let bytes = (*data).as_ref();
+            let mut reader = bytes.get(offset..).unwrap_or_default();
+            let symbols_size = reader.read_u64::<LE>()?;
+            let section_end = (offset + 8)
+                .checked_add(symbols_size as usize)
+                .filter(|end| *end <= bytes.len())
+                .ok_or_else(|| Error::InvalidAbi {
+                    reason: format!("symbols section overflows the file ({symbols_size} bytes)"),
+                })?;
+            let nb_symbols = reader.read_u32::<LE>()? as usize;
+            // the length of each symbol takes at least a byte
+            let mut spans = Vec::with_capacity(nb_symbols.min(reader.len()) + 1);
+            spans.push((0, 0));
+            for _ in 0..nb_symbols {
+                let len = reader.read_vu32()? as usize;
+                if len > reader.len() {
+                    return Err(Error::UnexpectedEof {
+                        offset: Some(bytes.len() as u64),
+                    });
+                }
+                let start = bytes.len() - reader.len();
+                spans.push((start, start + len));
+                reader = &reader[len..];
+            }
+            let end = bytes.len() - reader.len();
+            if end != section_end {
+                return Err(Error::InvalidAbi {
+                    reason: format!(
+                        "symbols section ends at {end} instead of {section_end} ({symbols_size} bytes)"
+                    ),
+                });
+            }
+            (spans, end)
+        };
+
+        Ok((Self::from_spans(data, spans)?, end))
+    }
+
+    fn from_spans(data: SymbolsData, spans: Vec<(usize, usize)>) -> Result<Self> {
+        let bytes = (*data).as_ref();
+        for &(start, end) in &spans {
+            if std::str::from_utf8(&bytes[start..end]).is_err() {
+                return Err(Error::InvalidUtf8 {
+                    offset: Some(start as u64),
+                });
+            }
+        }
+
+        let mut symbols = Self {
+            data,
+            spans: spans.into_boxed_slice(),
+            ids: HashTable::new(),
+            hasher: RandomState::new(),
+        };
+
+        let mut ids = HashTable::with_capacity(symbols.len());
+        for id in 0..symbols.len() {
+            let symbol = symbols.str_at(id);
+            ids.entry(
+                symbols.hasher.hash_one(symbol),
+                |other: &u32| symbols.str_at(*other as usize) == symbol,
+                |other: &u32| symbols.hasher.hash_one(symbols.str_at(*other as usize)),
+            )
+            .or_insert(id as u32);
+        }
+        symbols.ids = ids;
+
+        Ok(symbols)
+    }
+
+    pub fn from_bytes<R: std::io::Read>(reader: &mut R) -> Result<Self> {
         reader.read_abi_symbols()
     }
 
+    #[inline]
+    fn str_at(&self, id: usize) -> &str {
+        let (start, end) = self.spans[id];
+        let bytes = &(*self.data).as_ref()[start..end];
+        // SAFETY: every span is checked to be valid UTF-8 in `from_spans`,
+        // and `data` is never mutated once shared
+        unsafe { std::str::from_utf8_unchecked(bytes) }
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        (0..self.len()).map(|id| self.str_at(id))
+    }
+
     pub fn len(&self) -> usize {
-        self.symbols.len()
+        self.spans.len()
     }
 
     #[must_use]
@@ -300,9 +761,34 @@ impl AbiSymbols {
     }
 }
 
+impl AbiSerialize for AbiSymbols {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // the symbols section has no headers that precedes it
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the symbols section, prefixed by its byte size.
+    ///
+    /// The empty symbol at offset `0` is implicit and therefore not written.
+    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
+        use crate::varint::VarintWrite;
+
+        let mut section: Vec<u8> = Vec::new();
+        section.write_u32::<LE>((self.len() - 1) as u32)?;
+        for symbol in self.iter().skip(1) {
+            section.write_vu32(symbol.len() as u32)?;
+            section.write_all(symbol.as_bytes())?;
+        }
+
+        writer.write_u64::<LE>(section.len() as u64)?;
+        writer.write_all(&section)?;
+        Ok(8 + section.len())
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct AbiTypes {
-    types: Box<[Rc<AbiType>]>,
+    types: Box<[Arc<AbiType>]>,
     pub core: CoreType,
 }
 
@@ -320,10 +806,36 @@ impl serde::Serialize for AbiTypes {
 }
 
 impl AbiTypes {
-    pub fn from_bytes<R: std::io::Read>(
-        reader: &mut R,
-        symbols: &AbiSymbols,
-    ) -> std::io::Result<Self> {
+    /// Creates the types table from the given types.
+    ///
+    /// The offsets of the core types are looked up using `symbols`.
+    pub fn new(types: Vec<Arc<AbiType>>, symbols: &AbiSymbols) -> Self {
+        let mut core = CoreType::default();
+
+        for (i, ty) in types.iter().enumerate() {
+            if &symbols[ty.module] == "core" {
+                match &symbols[ty.name] {
+                    "String" => core.string = i as u32,
+                    "Array" => core.array = i as u32,
+                    "Map" => core.map = i as u32,
+                    "Table" => core.table = Some(i as u32),
+                    "Tensor" => core.tensor = Some(i as u32),
+                    "Buffer" => core.buffer = Some(i as u32),
+                    "Tuple" => core.tuple = Some(i as u32),
+                    "Error" => core.error = Some(i as u32),
+                    "Set" => core.set = Some(i as u32),
+                    _ => (),
+                }
+            }
+        }
+
+        Self {
+            types: Box::from(types),
+            core,
+        }
+    }
+
+    pub fn from_bytes<R: std::io::Read>(reader: &mut R, symbols: &AbiSymbols) -> Result<Self> {
         reader.read_abi_types(symbols)
     }
 
@@ -337,6 +849,35 @@ impl AbiTypes {
     }
 }
 
+impl AbiSerialize for AbiTypes {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // the types section has no headers that precedes it
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the types section, prefixed by its byte size.
+    fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        let nb_attrs: usize = self
+            .types
+            .iter()
+            .map(|ty| ty.attrs.as_ref().map(|attrs| attrs.len()).unwrap_or(0))
+            .sum();
+
+        let mut section: Vec<u8> = Vec::new();
+        section.write_u32::<LE>(self.types.len() as u32)?;
+        section.write_u32::<LE>(nb_attrs as u32)?;
+        for ty in self.types.iter() {
+            ty.write_raw_to(&mut section, abi)?;
+        }
+
+        writer.write_u64::<LE>(section.len() as u64)?;
+        writer.write_all(&section)?;
+        Ok(8 + section.len())
+    }
+}
+
+/// The ids of the `core` types known to the SDK, the native types that are not defined by
+/// every ABI are optional
 #[derive(Debug, Default, Clone, Copy, Serialize)]
 pub struct CoreType {
     pub string: u32,
@@ -348,24 +889,68 @@ pub struct CoreType {
     pub node_index: u32,
     pub node_time: u32,
     pub node_geo: u32,
+    pub table: Option<u32>,
+    pub tensor: Option<u32>,
+    pub buffer: Option<u32>,
+    pub tuple: Option<u32>,
+    pub error: Option<u32>,
+    pub set: Option<u32>,
 }
 
+/// The functions of an ABI, read-only as the lookups of [`Abi`] are built from them
 #[derive(Debug, Clone)]
 pub struct AbiFunctions {
-    pub functions: Vec<AbiFn>,
+    functions: Vec<AbiFn>,
+    /// function offset by `"<module>::<name>"` or `"<module>::<type>::<name>"` symbol ids
+    #[deprecated(
+        note = "use `Abi::get_fn_by_fqn` or `Abi::get_fn_by_module_type_and_name` instead"
+    )]
     pub functions_by_id: HashMap<String, u32>,
 }
 
 impl AbiFunctions {
+    #[allow(deprecated)]
+    pub fn new(functions: Vec<AbiFn>) -> Self {
+        let functions_by_id = functions
+            .iter()
+            .enumerate()
+            .map(|(offset, function)| (function.fqn(), offset as u32))
+            .collect();
+        Self {
+            functions,
+            functions_by_id,
+        }
+    }
+
     pub fn from_bytes<R: std::io::Read>(
         reader: &mut R,
         symbols: &AbiSymbols,
         types: &AbiTypes,
-    ) -> std::io::Result<Self> {
+    ) -> Result<Self> {
         reader.read_abi_functions(symbols, types)
     }
 }
 
+impl AbiSerialize for AbiFunctions {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // the functions section has no headers that precedes it
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the functions section, prefixed by its byte size.
+    fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        let mut section: Vec<u8> = Vec::new();
+        section.write_u32::<LE>(self.functions.len() as u32)?;
+        for function in &self.functions {
+            function.write_raw_to(&mut section, abi)?;
+        }
+
+        writer.write_u64::<LE>(section.len() as u64)?;
+        writer.write_all(&section)?;
+        Ok(8 + section.len())
+    }
+}
+
 impl serde::Serialize for AbiFunctions {
     fn serialize<S>(&self, serializer: S) -> std::prelude::v1::Result<S::Ok, S::Error>
     where
@@ -381,9 +966,16 @@ impl serde::Serialize for AbiFunctions {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
 pub struct AbiType {
+    /// The offset of this type in the ABI types
+    #[serde(skip)]
+    pub offset: u32,
     pub lib_name: u32,
     pub module: u32,
     pub name: u32,
+    #[serde(skip)]
+    pub attributes_offset: u32,
+    #[serde(skip)]
+    pub mapped_prog_type_offset: u32,
     pub mapped_abi_type_offset: u32,
     #[serde(skip)]
     pub masked_abi_type_offset: u32,
@@ -395,23 +987,11 @@ pub struct AbiType {
     pub attrs: Option<Box<[AbiAttr]>>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
-pub enum LazyAbiType {
-    #[serde(rename = "prog_type_offset")]
-    Offset(u32),
-    #[serde(
-        rename = "type",
-        serialize_with = "crate::serde_utils::serialize_type_as_fqn"
-    )]
-    Ref(Rc<AbiType>),
-}
-
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize)]
 pub struct AbiAttr {
     pub name: u32,
     pub abi_type: u32,
-    #[serde(flatten)]
-    pub prog_type_offset: RefCell<LazyAbiType>,
+    pub prog_type_offset: u32,
     #[serde(skip)]
     pub mapped_any_offset: u32,
     pub mapped_att_offset: u32,
@@ -420,13 +1000,17 @@ pub struct AbiAttr {
     pub mapped: bool,
 }
 
+impl AbiAttr {
+    /// Returns the program type of this attribute
+    #[inline]
+    pub fn prog_type(&self, abi: &Abi) -> Option<Arc<AbiType>> {
+        abi.types.get(self.prog_type_offset)
+    }
+}
+
 impl std::fmt::Display for AbiAttr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let attr_type = self.prog_type_offset.borrow();
-        match &*attr_type {
-            LazyAbiType::Offset(offset) => write!(f, "{}: Type#{offset}", self.name),
-            LazyAbiType::Ref(ty) => write!(f, "{}: {}::{}", self.name, ty.module, ty.name),
-        }
+        write!(f, "{}: Type#{}", self.name, self.prog_type_offset)
     }
 }
 
@@ -438,7 +1022,7 @@ pub struct AbiFn {
     pub lib_name: u32,
     pub params: Vec<AbiParam>,
     #[serde(serialize_with = "crate::serde_utils::serialize_type_as_fqn")]
-    pub return_type: Rc<AbiType>,
+    pub return_type: Arc<AbiType>,
     pub return_nullable: bool,
     pub is_task: bool,
 }
@@ -447,7 +1031,7 @@ pub struct AbiFn {
 pub struct AbiParam {
     pub name: u32,
     #[serde(serialize_with = "crate::serde_utils::serialize_type_as_fqn")]
-    pub r#type: Rc<AbiType>,
+    pub r#type: Arc<AbiType>,
     pub nullable: bool,
 }
 
@@ -481,71 +1065,50 @@ impl<T: Read> RequestHeadersRead for T {
 }
 
 pub trait AbiSymbolsRead {
-    fn read_abi_symbols(&mut self) -> std::io::Result<AbiSymbols>;
+    fn read_abi_symbols(&mut self) -> Result<AbiSymbols>;
 }
 
 impl<T: Read> AbiSymbolsRead for T {
-    fn read_abi_symbols(&mut self) -> std::io::Result<AbiSymbols> {
+    fn read_abi_symbols(&mut self) -> Result<AbiSymbols> {
         let _symbols_size = self.read_u64::<LE>()?;
         let nb_symbols = self.read_u32::<LE>()? as usize;
-        let mut symbols: Vec<Box<str>> = Vec::with_capacity(nb_symbols + 1);
-        symbols.push("".into());
+        // all the symbols are read into a single buffer
+        let mut data = Vec::new();
+        let mut spans = Vec::with_capacity(nb_symbols + 1);
+        spans.push((0, 0));
         for _ in 0..nb_symbols {
-            let len: u32 = self.read_vu32()?;
-            let mut buf = String::with_capacity(len as usize);
-            self.take(len as u64).read_to_string(&mut buf)?;
-            let buf_ptr = buf.as_ptr();
-            let string: Box<str> = buf.into();
-            debug_assert_eq!(buf_ptr, string.as_ptr(), "unexpected re-alloc");
-            symbols.push(string);
-        }
-
-        let mut id_by_name: HashMap<&'static str, u32> = HashMap::with_capacity(nb_symbols + 1);
-
-        let symbols = symbols.into_boxed_slice();
-        for (i, str) in symbols.iter().enumerate() {
-            // SAFETY:
-            // We boxed the symbols vec and we know that for the lifetime of an ABI
-            // we never mutate `symbols` again. Ever.
-            // We also don't give direct access to `symbols`, nor `id_by_name`.
-            // Therefore, it is fine to lie to the compiler and tell it that
-            // those references are static because they are never gonna be moved.
-            //
-            // To make it crystal clear, we only impl std::ops::Index for AbiSymbols
-            // so we don't even leak those `&'static str`, we give references to the boxed slice of boxed str
-            let key: &'static str = unsafe { std::mem::transmute(&**str) };
-            id_by_name.insert(key, i as u32);
-        }
-
-        Ok(AbiSymbols {
-            symbols,
-            id_by_name,
-        })
+            let len = self.read_vu32()? as usize;
+            let start = data.len();
+            data.resize(start + len, 0);
+            self.read_exact(&mut data[start..])?;
+            spans.push((start, data.len()));
+        }
+
+        AbiSymbols::from_spans(Arc::new(data), spans)
     }
 }
 
 pub trait AbiTypesRead {
-    fn read_abi_types(&mut self, symbols: &AbiSymbols) -> std::io::Result<AbiTypes>;
+    fn read_abi_types(&mut self, symbols: &AbiSymbols) -> Result<AbiTypes>;
 }
 
 impl<T: Read> AbiTypesRead for T {
-    fn read_abi_types(&mut self, symbols: &AbiSymbols) -> std::io::Result<AbiTypes> {
+    fn read_abi_types(&mut self, symbols: &AbiSymbols) -> Result<AbiTypes> {
         // types
         let _types_bin_size = self.read_u64::<LE>()?; // types binary size
         let nb_types = self.read_u32::<LE>()?;
         let _nb_attrs = self.read_u32::<LE>()?;
 
-        let mut types: Vec<Rc<AbiType>> = Vec::with_capacity(nb_types as usize);
-        let mut core = CoreType::default();
+        let mut types: Vec<Arc<AbiType>> = Vec::with_capacity(nb_types as usize);
 
-        for i in 0..nb_types {
+        for offset in 0..nb_types {
             // parse types
             let module: u32 = self.read_vu32()?;
             let name: u32 = self.read_vu32()?;
             let lib_name: u32 = self.read_vu32()?;
             let attributes_len: u32 = self.read_vu32()?;
-            let _attributes_offset: u32 = self.read_vu32()?;
-            let _mapped_prog_type_offset: u32 = self.read_vu32()?;
+            let attributes_offset: u32 = self.read_vu32()?;
+            let mapped_prog_type_offset: u32 = self.read_vu32()?;
             let mapped_abi_type_offset: u32 = self.read_vu32()?;
             let masked_abi_type_offset: u32 = self.read_vu32()?;
             let nullable_nb_bytes: u32 = self.read_vu32()?;
@@ -569,11 +1132,6 @@ impl<T: Read> AbiTypesRead for T {
                     let nullable = (flags & 1) != 0;
                     let mapped = (flags & (1 << 1)) != 0;
 
-                    let prog_type_offset = match types.get(prog_type_offset as usize) {
-                        Some(ty) => RefCell::new(LazyAbiType::Ref(ty.clone())),
-                        None => RefCell::new(LazyAbiType::Offset(prog_type_offset)),
-                    };
-
                     attrs.push(AbiAttr {
                         name,
                         abi_type,
@@ -590,19 +1148,13 @@ impl<T: Read> AbiTypesRead for T {
                 None
             };
 
-            if &symbols[module] == "core" {
-                match &symbols[name] {
-                    "String" => core.string = i,
-                    "Array" => core.array = i,
-                    "Map" => core.map = i,
-                    _ => (),
-                }
-            }
-
-            let ty = Rc::new(AbiType {
+            let ty = Arc::new(AbiType {
+                offset,
                 module,
                 name,
                 lib_name,
+                attributes_offset,
+                mapped_prog_type_offset,
                 mapped_abi_type_offset,
                 masked_abi_type_offset,
                 nullable_nb_bytes,
@@ -616,26 +1168,12 @@ impl<T: Read> AbiTypesRead for T {
             types.push(ty);
         }
 
-        for ty in &types {
-            if let Some(attrs) = &ty.attrs {
-                for attr in attrs.iter() {
-                    let mut ty = attr.prog_type_offset.borrow_mut();
-                    if let LazyAbiType::Offset(offset) = *ty {
-                        *ty = LazyAbiType::Ref(types[offset as usize].clone());
-                    }
-                }
-            }
-        }
-
-        Ok(AbiTypes {
-            types: Box::from(types),
-            core,
-        })
+        Ok(AbiTypes::new(types, symbols))
     }
 }
 
 impl AbiTypes {
-    pub fn get(&self, id: u32) -> Option<Rc<AbiType>> {
+    pub fn get(&self, id: u32) -> Option<Arc<AbiType>> {
         self.types.get(id as usize).cloned()
     }
 }
@@ -644,8 +1182,8 @@ impl AbiTypes {
 pub struct ModVar {
     module: u32,
     name: u32,
-    #[serde(rename = "type", flatten)]
-    ty: LazyAbiType,
+    #[serde(rename = "prog_type_offset")]
+    ty: u32,
     nullable: bool,
 }
 
@@ -658,16 +1196,8 @@ impl IntoIterator for AbiFunctions {
     }
 }
 
-impl std::ops::Deref for AbiSymbols {
-    type Target = [Box<str>];
-
-    fn deref(&self) -> &Self::Target {
-        &self.symbols
-    }
-}
-
 impl std::ops::Deref for AbiTypes {
-    type Target = [Rc<AbiType>];
+    type Target = [Arc<AbiType>];
 
     fn deref(&self) -> &Self::Target {
         &self.types
@@ -675,25 +1205,19 @@ impl std::ops::Deref for AbiTypes {
 }
 
 impl std::ops::Deref for AbiFunctions {
-    type Target = Vec<AbiFn>;
+    type Target = [AbiFn];
 
     fn deref(&self) -> &Self::Target {
         &self.functions
     }
 }
 
-impl std::ops::DerefMut for AbiFunctions {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.functions
-    }
-}
-
 pub trait AbiFunctionsRead {
     fn read_abi_functions(
         &mut self,
         symbols: &AbiSymbols,
         types: &AbiTypes,
-    ) -> std::io::Result<AbiFunctions>;
+    ) -> Result<AbiFunctions>;
 }
 
 impl<T: Read> AbiFunctionsRead for T {
@@ -701,14 +1225,13 @@ impl<T: Read> AbiFunctionsRead for T {
         &mut self,
         _symbols: &AbiSymbols,
         types: &AbiTypes,
-    ) -> std::io::Result<AbiFunctions> {
+    ) -> Result<AbiFunctions> {
         let _functions_bin_size = self.read_u64::<LE>()?;
         let functions_len = self.read_u32::<LE>()?;
 
         let mut functions = Vec::with_capacity(functions_len as usize);
-        let mut functions_by_id = HashMap::with_capacity(functions_len as usize);
 
-        for fn_idx in 0..functions_len {
+        for _ in 0..functions_len {
             let module: u32 = self.read_vu32()?;
             let ty: u32 = self.read_vu32()?;
             let name: u32 = self.read_vu32()?;
@@ -722,10 +1245,17 @@ impl<T: Read> AbiFunctionsRead for T {
                 params.push(AbiParam {
                     name: param_symbol,
                     nullable: param_nullable,
-                    r#type: types[param_type].clone(),
+                    r#type: types.get(param_type).ok_or(Error::UnknownType {
+                        id: param_type,
+                        offset: None,
+                    })?,
                 });
             }
             let return_type: u32 = self.read_vu32()?;
+            let return_type = types.get(return_type).ok_or(Error::UnknownType {
+                id: return_type,
+                offset: None,
+            })?;
             let flags = self.read_u8()?;
             let return_nullable = (flags & 1) != 0;
             let is_task = (flags & (1 << 1)) != 0;
@@ -737,17 +1267,13 @@ impl<T: Read> AbiFunctionsRead for T {
                 name,
                 is_task,
                 return_nullable,
-                return_type: types[return_type].clone(),
+                return_type,
                 params,
             };
-            functions_by_id.insert(function.fqn(), fn_idx);
             functions.push(function);
         }
 
-        Ok(AbiFunctions {
-            functions,
-            functions_by_id,
-        })
+        Ok(AbiFunctions::new(functions))
     }
 }
 
@@ -761,6 +1287,114 @@ impl AbiType {
         let name = &abi.symbols[self.name];
         format!("{module}::{name}")
     }
+
+    /// Returns `true` if this type is the type of `abi` at its offset, and not the type of another
+    /// ABI that happens to have the same offset
+    pub fn is_type_of(&self, abi: &Abi) -> bool {
+        abi.types
+            .types
+            .get(self.offset as usize)
+            .is_some_and(|ty| std::ptr::eq(&**ty, self))
+    }
+
+    /// Returns `true` if this type is `parent` or inherits from it, both being types of the same ABI.
+    ///
+    /// The ABI does not record inheritance: a type is deemed to inherit from `parent` when its
+    /// attributes start with all the attributes of `parent`.
+    pub fn is_subtype_of(&self, parent: &AbiType) -> bool {
+        if self.offset == parent.offset {
+            return true;
+        }
+        if self.is_enum || self.is_native || parent.is_enum || parent.is_native {
+            return false;
+        }
+        let attrs = parent.attrs.as_deref().unwrap_or_default();
+        let sub_attrs = self.attrs.as_deref().unwrap_or_default();
+        !attrs.is_empty()
+            && sub_attrs.len() >= attrs.len()
+            && attrs.iter().zip(sub_attrs).all(|(attr, sub_attr)| {
+                attr.name == sub_attr.name && attr.prog_type_offset == sub_attr.prog_type_offset
+            })
+    }
+
+    /// Returns the offset of the attribute `name` of this type, if any.
+    ///
+    /// The offsets of the attributes of the types of `abi` are cached by `abi` on first use.
+    pub fn attr_offset(&self, name: &str, abi: &Abi) -> Option<usize> {
+        let symbol = abi.symbols.get(name)?;
+        match abi.index.attrs.get(self.offset as usize) {
+            Some(attrs) if self.is_type_of(abi) => attrs
+                .get_or_init(|| {
+                    let attrs = self.attrs.as_deref().unwrap_or_default();
+                    let mut offsets = HashMap::with_capacity(attrs.len());
+                    for (offset, attr) in attrs.iter().enumerate() {
+                        offsets.entry(attr.name).or_insert(offset);
+                    }
+                    offsets
+                })
+                .get(&symbol)
+                .copied(),
+            // not a type of `abi`, its symbols are then only valid if `abi` is a copy of its ABI
+            _ => self
+                .attrs
+                .as_deref()?
+                .iter()
+                .position(|attr| attr.name == symbol),
+        }
+    }
+}
+
+impl AbiSerialize for AbiType {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // ABI types have no headers that precedes them
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the type definition followed by its attributes
+    fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        use crate::varint::VarintWrite;
+
+        let attrs = self.attrs.as_deref().unwrap_or_default();
+        let mut n = writer.write_vu32(self.module)?;
+        n += writer.write_vu32(self.name)?;
+        n += writer.write_vu32(self.lib_name)?;
+        n += writer.write_vu32(attrs.len() as u32)?;
+        n += writer.write_vu32(self.attributes_offset)?;
+        n += writer.write_vu32(self.mapped_prog_type_offset)?;
+        n += writer.write_vu32(self.mapped_abi_type_offset)?;
+        n += writer.write_vu32(self.masked_abi_type_offset)?;
+        n += writer.write_vu32(self.nullable_nb_bytes)?;
+        let flags = (self.is_native as u8)
+            | ((self.is_abstract as u8) << 1)
+            | ((self.is_enum as u8) << 2)
+            | ((self.is_masked as u8) << 3);
+        writer.write_u8(flags)?;
+        n += 1;
+        for attr in attrs {
+            n += attr.write_raw_to(writer, abi)?;
+        }
+        Ok(n)
+    }
+}
+
+impl AbiSerialize for AbiAttr {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // ABI attributes have no headers that precedes them
+        self.write_raw_to(writer, abi)
+    }
+
+    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
+        use crate::varint::VarintWrite;
+
+        let mut n = writer.write_vu32(self.name)?;
+        n += writer.write_vu32(self.abi_type)?;
+        n += writer.write_vu32(self.prog_type_offset)?;
+        n += writer.write_vu32(self.mapped_any_offset)?;
+        n += writer.write_vu32(self.mapped_att_offset)?;
+        writer.write_u8(self.sbi_type)?;
+        writer.write_u8((self.nullable as u8) | ((self.mapped as u8) << 1))?;
+        Ok(n + 2)
+    }
 }
 
 impl AbiFn {
@@ -770,10 +1404,51 @@ impl AbiFn {
             None => format!("{}::{}", self.module, self.name),
         }
     }
+
+    /// Returns `"<module>::<name>"` or, for type methods, `"<module>::<type>::<name>"`
+    pub fn named_fqn(&self, abi: &Abi) -> String {
+        self.named_fqn_with(&abi.symbols)
+    }
+
+    fn named_fqn_with(&self, symbols: &AbiSymbols) -> String {
+        let module = &symbols[self.module];
+        let name = &symbols[self.name];
+        match self.r#type {
+            Some(ty) => format!("{module}::{}::{name}", &symbols[ty]),
+            None => format!("{module}::{name}"),
+        }
+    }
+}
+
+impl AbiSerialize for AbiFn {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        // ABI functions have no headers that precedes them
+        self.write_raw_to(writer, abi)
+    }
+
+    /// Writes the function definition followed by its parameters
+    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
+        use crate::varint::VarintWrite;
+
+        let mut n = writer.write_vu32(self.module)?;
+        n += writer.write_vu32(self.r#type.unwrap_or(0))?;
+        n += writer.write_vu32(self.name)?;
+        n += writer.write_vu32(self.lib_name)?;
+        n += writer.write_vu32(self.params.len() as u32)?;
+        for param in &self.params {
+            writer.write_u8(param.nullable as u8)?;
+            n += 1;
+            n += writer.write_vu32(param.r#type.offset)?;
+            n += writer.write_vu32(param.name)?;
+        }
+        n += writer.write_vu32(self.return_type.offset)?;
+        writer.write_u8((self.return_nullable as u8) | ((self.is_task as u8) << 1))?;
+        Ok(n + 1)
+    }
 }
 
 impl std::ops::Index<u32> for AbiTypes {
-    type Output = Rc<AbiType>;
+    type Output = Arc<AbiType>;
 
     #[inline]
     fn index(&self, index: u32) -> &Self::Output {
@@ -782,7 +1457,7 @@ impl std::ops::Index<u32> for AbiTypes {
 }
 
 impl std::ops::Index<usize> for AbiTypes {
-    type Output = Rc<AbiType>;
+    type Output = Arc<AbiType>;
 
     #[inline]
     fn index(&self, index: usize) -> &Self::Output {
@@ -791,9 +1466,22 @@ impl std::ops::Index<usize> for AbiTypes {
 }
 
 impl AbiSymbols {
+    /// Returns the symbol with the given `id`, if any
+    #[inline]
+    pub fn resolve(&self, id: u32) -> Option<&str> {
+        if (id as usize) < self.len() {
+            Some(self.str_at(id as usize))
+        } else {
+            None
+        }
+    }
+
     #[inline]
     pub fn get(&self, symbol: &str) -> Option<u32> {
-        self.id_by_name.get(symbol).copied()
+        let hash = self.hasher.hash_one(symbol);
+        self.ids
+            .find(hash, |id| self.str_at(*id as usize) == symbol)
+            .copied()
     }
 }
 
@@ -802,7 +1490,7 @@ impl std::ops::Index<u32> for AbiSymbols {
 
     #[inline]
     fn index(&self, index: u32) -> &Self::Output {
-        &self.symbols[index as usize]
+        self.str_at(index as usize)
     }
 }
 
@@ -811,6 +1499,453 @@ impl std::ops::Index<usize> for AbiSymbols {
 
     #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.symbols[index]
+        self.str_at(index)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::primitive;
+
+    pub(crate) fn ty(
+        offset: u32,
+        module: u32,
+        name: u32,
+        attributes_offset: u32,
+        attrs: Option<Vec<AbiAttr>>,
+    ) -> AbiType {
+        AbiType {
+            offset,
+            lib_name: 1,
+            module,
+            name,
+            attributes_offset,
+            mapped_prog_type_offset: offset,
+            mapped_abi_type_offset: offset,
+            masked_abi_type_offset: 0,
+            nullable_nb_bytes: 0,
+            is_native: attrs.is_none(),
+            is_abstract: false,
+            is_enum: false,
+            is_masked: false,
+            attrs: attrs.map(Vec::into_boxed_slice),
+        }
+    }
+
+    pub(crate) fn attr(name: u32, ty: u32, offset: u32, sbi_type: u8) -> AbiAttr {
+        AbiAttr {
+            name,
+            abi_type: ty,
+            prog_type_offset: ty,
+            mapped_any_offset: 0,
+            mapped_att_offset: offset,
+            sbi_type,
+            nullable: false,
+            mapped: true,
+        }
+    }
+
+    /// Builds a small synthetic ABI that mimics what GreyCat would generate for:
+    ///
+    /// ```gcl
+    /// type Person { name: String; age: int; }
+    /// enum Status { Active; Inactive; }
+    /// fn add(a: int, b: int): int;
+    /// ```
+    pub(crate) fn project_abi() -> Abi {
+        let symbols = AbiSymbols::new([
+            "std", "core", "project", "int", "String", "Array", "Map", "Person", "name", "age",
+            "Status", "Active", "Inactive", "add", "a", "b",
+        ]);
+
+        let status = AbiType {
+            lib_name: 3,
+            is_enum: true,
+            ..ty(
+                5,
+                3,
+                11,
+                2,
+                Some(vec![
+                    attr(12, 5, 0, primitive::NULL),
+                    attr(13, 5, 1, primitive::NULL),
+                ]),
+            )
+        };
+        let types = vec![
+            ty(0, 2, 4, 0, None),
+            ty(1, 2, 5, 0, None),
+            ty(2, 2, 6, 0, None),
+            ty(3, 2, 7, 0, None),
+            AbiType {
+                lib_name: 3,
+                ..ty(
+                    4,
+                    3,
+                    8,
+                    0,
+                    Some(vec![
+                        attr(9, 1, 0, primitive::OBJECT),
+                        attr(10, 0, 1, primitive::INT),
+                    ]),
+                )
+            },
+            status,
+        ];
+        let types = AbiTypes::new(types.into_iter().map(Arc::new).collect(), &symbols);
+
+        let int = types[0u32].clone();
+        let functions = AbiFunctions::new(vec![AbiFn {
+            module: 3,
+            r#type: None,
+            name: 14,
+            lib_name: 3,
+            params: vec![
+                AbiParam {
+                    name: 15,
+                    r#type: int.clone(),
+                    nullable: false,
+                },
+                AbiParam {
+                    name: 16,
+                    r#type: int.clone(),
+                    nullable: false,
+                },
+            ],
+            return_type: int,
+            return_nullable: false,
+            is_task: false,
+        }]);
+
+        let headers = AbiHeaders {
+            headers: RequestHeaders {
+                protocol: 1,
+                magic: 0x1337,
+                version: 42,
+            },
+            crc: 0xdead_beef,
+        };
+
+        Abi::from_parts(headers, symbols, types, functions, None).unwrap()
+    }
+
+    /// Rebuilds `abi` after letting `edit` modify the attributes of each of its types, given their name.
+    ///
+    /// The `extra_symbols` are appended to the symbols of `abi`.
+    pub(crate) fn rebuild_abi(
+        abi: &Abi,
+        extra_symbols: &[&str],
+        mut edit: impl FnMut(&str, &mut Vec<AbiAttr>),
+    ) -> Abi {
+        let symbols = AbiSymbols::new(
+            abi.symbols
+                .iter()
+                .skip(1)
+                .chain(extra_symbols.iter().copied()),
+        );
+        let types = abi
+            .types
+            .iter()
+            .map(|ty| {
+                let mut ty = AbiType::clone(ty);
+                let mut attrs = ty.attrs.take().map(Vec::from).unwrap_or_default();
+                edit(&abi.symbols[ty.name], &mut attrs);
+                ty.nullable_nb_bytes = attrs
+                    .iter()
+                    .filter(|attr| attr.nullable)
+                    .count()
+                    .div_ceil(8) as u32;
+                ty.attrs = (!attrs.is_empty()).then(|| attrs.into_boxed_slice());
+                Arc::new(ty)
+            })
+            .collect();
+        let types = AbiTypes::new(types, &symbols);
+        let functions = AbiFunctions::new(abi.functions.to_vec());
+
+        Abi::from_parts(abi.headers, symbols, types, functions, None).unwrap()
+    }
+
+    /// Extends [`project_abi`] with a nullable enum attribute on `Person`:
+    ///
+    /// ```gcl
+    /// type Person { name: String; age: int; status: Status?; }
+    /// ```
+    pub(crate) fn person_status_abi() -> Abi {
+        let abi = project_abi();
+        // the id of the extra symbol
+        let name = abi.symbols.len() as u32;
+        let status = abi.get_type_by_fqn("project::Status").unwrap().offset;
+        rebuild_abi(&abi, &["status"], |ty, attrs| {
+            if ty == "Person" {
+                let mut attr = attr(name, status, attrs.len() as u32, primitive::ENUM);
+                attr.nullable = true;
+                attrs.push(attr);
+            }
+        })
+    }
+
+    /// Extends [`project_abi`] with the native `core` types `Table`, `Tensor`, `Buffer`,
+    /// `Tuple`, `Error` and `Set`
+    pub(crate) fn native_abi() -> Abi {
+        let abi = project_abi();
+        let natives = ["Table", "Tensor", "Buffer", "Tuple", "Error", "Set"];
+        let symbols = AbiSymbols::new(abi.symbols.iter().skip(1).chain(natives));
+        let mut types: Vec<_> = abi.types.iter().cloned().collect();
+        for native in natives {
+            let offset = types.len() as u32;
+            types.push(Arc::new(ty(
+                offset,
+                2,
+                symbols.get(native).unwrap(),
+                0,
+                None,
+            )));
+        }
+        let types = AbiTypes::new(types, &symbols);
+        let functions = AbiFunctions::new(abi.functions.to_vec());
+
+        Abi::from_parts(abi.headers, symbols, types, functions, None).unwrap()
+    }
+
+    #[test]
+    fn library_loaders() {
+        use crate::deserialize::AbiDeserialize;
+        use crate::gc_object::GcObject;
+        use crate::library::{Library, TypeLoader};
+        use crate::value::Value;
+        use crate::varint::{VarintRead, VarintWrite};
+
+        /// codec of a native `project::Point`, loaded as an object of its two coordinates
+        struct PointLoader;
+
+        impl TypeLoader for PointLoader {
+            fn load<'abi>(
+                &self,
+                mut reader: &mut dyn Read,
+                ty: &AbiType,
+                abi: &'abi Abi,
+            ) -> Result<Value<'abi>> {
+                let x = reader.read_vi64()?;
+                let y = reader.read_vi64()?;
+                let ty = abi.types.get(ty.offset).unwrap();
+                Ok(Value::Obj(GcObject::new(
+                    ty,
+                    Some([Value::Int(x), Value::Int(y)]),
+                )))
+            }
+
+            fn save(
+                &self,
+                value: &GcObject,
+                mut writer: &mut dyn Write,
+                _abi: &Abi,
+            ) -> Result<usize> {
+                let mut n = 0;
+                for coordinate in value.values.as_deref().unwrap_or_default() {
+                    let Value::Int(v) = coordinate else {
+                        return Err(Error::ValueMismatch {
+                            expected: "int".to_string(),
+                            got: format!("{coordinate:?}"),
+                        });
+                    };
+                    n += writer.write_vi64(*v)?;
+                }
+                Ok(n)
+            }
+        }
+
+        struct ProjectLibrary;
+
+        impl Library for ProjectLibrary {
+            fn name(&self) -> &'static str {
+                "project"
+            }
+
+            fn configure(
+                &self,
+                loaders: &mut BTreeMap<&'static str, Box<dyn TypeLoader>>,
+            ) -> Result<()> {
+                loaders.insert("project::Point", Box::new(PointLoader));
+                Ok(())
+            }
+
+            fn init(&mut self, abi: &Abi) -> Result<()> {
+                abi.get_type_by_fqn("project::Point")
+                    .map(|_| ())
+                    .ok_or(Error::UnknownTypeName {
+                        fqn: "project::Point".to_string(),
+                    })
+            }
+        }
+
+        let abi = project_abi();
+        let symbols = AbiSymbols::new(abi.symbols.iter().skip(1).chain(["Point"]));
+        let mut types: Vec<_> = abi.types.iter().cloned().collect();
+        types.push(Arc::new(AbiType {
+            lib_name: 3,
+            ..ty(6, 3, symbols.get("Point").unwrap(), 0, None)
+        }));
+        let types = AbiTypes::new(types, &symbols);
+        let functions = AbiFunctions::new(abi.functions.to_vec());
+        let mut bytes = vec![primitive::OBJECT];
+        bytes.write_vu32(6).unwrap();
+        bytes.write_vi64(-4).unwrap();
+        bytes.write_vi64(2).unwrap();
+
+        let without = Abi::from_parts(
+            abi.headers,
+            symbols.clone(),
+            types.clone(),
+            functions.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            (&*bytes).read_value(&without),
+            Err(Error::Unsupported { .. })
+        ));
+
+        let abi = Abi::from_parts(
+            abi.headers,
+            symbols,
+            types,
+            functions,
+            Some(vec![Box::new(ProjectLibrary)]),
+        )
+        .unwrap();
+        assert_eq!(abi.libraries.len(), 2);
+        let point = (&*bytes).read_value(&abi).unwrap();
+        let Value::Obj(obj) = &point else {
+            panic!("expected a point, got {point:?}");
+        };
+        assert_eq!(obj.ty.offset, 6);
+        assert_eq!(
+            obj.values.as_deref(),
+            Some(&[Value::Int(-4), Value::Int(2)][..])
+        );
+
+        // and written back as a native `project::Point`
+        let mut written = Vec::new();
+        let n = point.write_to(&mut written, &abi).unwrap();
+        assert_eq!((written, n), (bytes.clone(), bytes.len()));
+        assert!(matches!(
+            point.write_to(&mut Vec::new(), &without),
+            Err(Error::Unsupported { .. })
+        ));
+    }
+
+    #[test]
+    fn abi_write_read_roundtrip() {
+        let abi = project_abi();
+        let mut bytes = Vec::new();
+        let n = abi.write_to(&mut bytes, &abi).unwrap();
+        assert_eq!(n, bytes.len());
+
+        let read = Abi::new(&*bytes, None).unwrap();
+        assert_eq!(read.headers.crc, 0xdead_beef);
+        assert_eq!(read.symbols.len(), abi.symbols.len());
+        assert_eq!(read.types.core.string, 1);
+        assert_eq!(read.functions.len(), 1);
+
+        let person = read.get_type_by_fqn("project::Person").unwrap();
+        let attrs = person.attrs.as_deref().unwrap();
+        assert_eq!(&read.symbols[attrs[1].name], "age");
+        assert_eq!(attrs[0].prog_type(&read).unwrap().offset, 1);
+
+        let mut rewritten = Vec::new();
+        read.write_to(&mut rewritten, &read).unwrap();
+        assert_eq!(bytes, rewritten);
+    }
+
+    #[test]
+    fn abi_indexed_lookups() {
+        let abi = project_abi();
+
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+        assert_eq!(person.offset, 4);
+        assert_eq!(
+            abi.get_type_by_module_and_name("project", "Person"),
+            Some(person.clone())
+        );
+        assert!(abi.get_type_by_fqn("project::Nope").is_none());
+
+        // the cached offsets of `swapped` do not apply to the `Person` of another ABI
+        let swapped = rebuild_abi(&abi, &[], |name, attrs| {
+            if name == "Person" {
+                attrs.reverse();
+            }
+        });
+        let swapped_person = swapped.get_type_by_fqn("project::Person").unwrap();
+        assert!(person.is_type_of(&abi) && !person.is_type_of(&swapped));
+        assert_eq!(swapped_person.attr_offset("name", &swapped), Some(1));
+        assert_eq!(person.attr_offset("name", &swapped), Some(0));
+
+        let add = abi.get_fn_by_fqn("project::add").unwrap();
+        assert_eq!(add.named_fqn(&abi), "project::add");
+        assert_eq!(
+            abi.get_fn_by_module_type_and_name("project", None, "add"),
+            Some(add)
+        );
+        assert!(abi.get_fn_by_fqn("project::Person::add").is_none());
+
+        // the method `Person::add` does not shadow the function `add`
+        let mut functions = abi.functions.to_vec();
+        functions.push(AbiFn {
+            r#type: Some(person.name),
+            ..add.clone()
+        });
+        let abi = Abi::from_parts(
+            abi.headers,
+            abi.symbols.clone(),
+            abi.types.clone(),
+            AbiFunctions::new(functions),
+            None,
+        )
+        .unwrap();
+        let method = abi.get_fn_by_fqn("project::Person::add").unwrap();
+        assert_eq!(method.named_fqn(&abi), "project::Person::add");
+        assert_eq!(abi.get_fn_offset_by_fqn("project::Person::add"), Some(1));
+        assert_eq!(
+            abi.get_fn_by_module_type_and_name("project", Some("Person"), "add"),
+            Some(method)
+        );
+        assert_eq!(abi.get_fn_offset_by_fqn("project::add"), Some(0));
+        #[allow(deprecated)]
+        let by_id = &abi.functions.functions_by_id;
+        assert_eq!(by_id.get(&method.fqn()), Some(&1));
+    }
+
+    #[test]
+    fn abi_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Abi>();
+        assert_send_sync::<crate::value::Value<'static>>();
+    }
+
+    #[test]
+    fn abi_headers_mismatch() {
+        let abi = project_abi();
+        assert_eq!(abi.check_abi_headers(&abi.headers), Ok(()));
+
+        let mut headers = abi.headers;
+        headers.headers.version = 43;
+        assert_eq!(
+            abi.check_headers(&headers.headers),
+            Err(AbiMismatch::Version {
+                expected: 42,
+                got: 43
+            })
+        );
+
+        headers.headers.version = 42;
+        headers.crc = 0xcafe;
+        assert_eq!(
+            abi.check_abi_headers(&headers),
+            Err(AbiMismatch::Crc {
+                expected: 0xdead_beef,
+                got: 0xcafe
+            })
+        );
     }
 }
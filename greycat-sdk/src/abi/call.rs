@@ -0,0 +1,99 @@
+use crate::conform;
+use crate::error::{ArgError, Error, Result};
+use crate::serialize::AbiSerialize;
+use crate::value::Value;
+
+use super::{Abi, AbiFn};
+
+impl AbiFn {
+    /// Checks that `args` match the parameters of the function, see [`Value::conforms_to`].
+    ///
+    /// Fails with [`Error::InvalidArgs`] listing every violation of every parameter.
+    pub fn validate_args(&self, args: &[Value], abi: &Abi) -> Result<()> {
+        if args.len() != self.params.len() {
+            return Err(Error::ArgCount {
+                function: self.named_fqn(abi),
+                expected: self.params.len(),
+                got: args.len(),
+            });
+        }
+
+        let mut errors = Vec::new();
+        for (param, arg) in self.params.iter().zip(args) {
+            let mut violations = Vec::new();
+            conform::check(arg, &param.r#type, param.nullable, abi, "", &mut violations);
+            errors.extend(violations.into_iter().map(|violation| ArgError {
+                function: self.named_fqn(abi),
+                param: abi.symbols[param.name].to_string(),
+                violation,
+            }));
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::InvalidArgs(errors));
+        }
+        Ok(())
+    }
+
+    /// Validates `args` and encodes the body of a request calling this function: the ABI
+    /// request headers followed by each argument.
+    pub fn encode_request(&self, args: &[Value], abi: &Abi) -> Result<Vec<u8>> {
+        self.validate_args(args, abi)?;
+
+        let mut body = Vec::new();
+        abi.headers.headers.write_to(&mut body, abi)?;
+        for arg in args {
+            arg.write_to(&mut body, abi)?;
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::abi::RequestHeadersRead;
+    use crate::deserialize::AbiDeserialize;
+
+    #[test]
+    fn validate_args_per_param() {
+        let abi = project_abi();
+        let add = abi.get_fn_by_fqn("project::add").unwrap();
+
+        let args = [Value::Int(1), Value::Int(2)];
+        let body = add.encode_request(&args, &abi).unwrap();
+        assert_eq!(&body[..8], &[1, 0, 0x37, 0x13, 42, 0, 0, 0]);
+        let mut reader = &body[..];
+        assert_eq!(reader.read_request_headers().unwrap(), abi.headers.headers);
+        let decoded = [
+            reader.read_value(&abi).unwrap(),
+            reader.read_value(&abi).unwrap(),
+        ];
+        assert_eq!(decoded, args);
+        assert!(reader.is_empty());
+
+        let err = add
+            .encode_request(&[Value::Int(1), Value::Float(2.0.into())], &abi)
+            .unwrap_err();
+        assert!(matches!(&err, Error::InvalidArgs(errors) if errors.len() == 1));
+
+        let err = add
+            .validate_args(&[Value::Int(1), Value::String("2".into())], &abi)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "param `b` of `project::add` expects int, got String"
+        );
+
+        let err = add.validate_args(&[Value::Int(1)], &abi).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ArgCount {
+                expected: 2,
+                got: 1,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,768 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::abi::{Abi, AbiAttr, AbiFn, AbiType};
+
+/// Whether a change breaks the clients built against the old ABI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compatibility {
+    Compatible,
+    Breaking,
+}
+
+/// A single change between two ABIs, everything is referred to by name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AbiChangeKind {
+    TypeAdded {
+        ty: String,
+    },
+    TypeRemoved {
+        ty: String,
+    },
+    EnumVariantAdded {
+        ty: String,
+        variant: String,
+    },
+    EnumVariantRemoved {
+        ty: String,
+        variant: String,
+    },
+    AttrAdded {
+        ty: String,
+        attr: String,
+        nullable: bool,
+    },
+    AttrRemoved {
+        ty: String,
+        attr: String,
+    },
+    AttrTypeChanged {
+        ty: String,
+        attr: String,
+        old: String,
+        new: String,
+    },
+    AttrNullabilityChanged {
+        ty: String,
+        attr: String,
+        nullable: bool,
+    },
+    AttrMoved {
+        ty: String,
+        attr: String,
+        old: usize,
+        new: usize,
+    },
+    FnAdded {
+        function: String,
+    },
+    FnRemoved {
+        function: String,
+    },
+    ParamAdded {
+        function: String,
+        param: String,
+    },
+    ParamRemoved {
+        function: String,
+        param: String,
+    },
+    ParamTypeChanged {
+        function: String,
+        param: String,
+        old: String,
+        new: String,
+    },
+    ParamNullabilityChanged {
+        function: String,
+        param: String,
+        nullable: bool,
+    },
+    ReturnTypeChanged {
+        function: String,
+        old: String,
+        new: String,
+    },
+    ReturnNullabilityChanged {
+        function: String,
+        nullable: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AbiChange {
+    #[serde(flatten)]
+    pub kind: AbiChangeKind,
+    pub compatibility: Compatibility,
+}
+
+impl AbiChange {
+    #[inline]
+    pub fn is_breaking(&self) -> bool {
+        self.compatibility == Compatibility::Breaking
+    }
+}
+
+/// The list of changes between two ABIs, see [`diff`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AbiDiff {
+    pub changes: Vec<AbiChange>,
+}
+
+impl AbiDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(AbiChange::is_breaking)
+    }
+
+    pub fn breaking(&self) -> impl Iterator<Item = &AbiChange> {
+        self.changes.iter().filter(|change| change.is_breaking())
+    }
+
+    fn push(&mut self, kind: AbiChangeKind, compatibility: Compatibility) {
+        self.changes.push(AbiChange {
+            kind,
+            compatibility,
+        });
+    }
+}
+
+/// Computes the changes needed to go from the `old` ABI to the `new` one.
+///
+/// Types, attributes, functions and parameters are matched by name, not by symbol id, so that
+/// two ABIs generated from different projects can be compared.
+pub fn diff(old: &Abi, new: &Abi) -> AbiDiff {
+    use Compatibility::*;
+
+    let mut diff = AbiDiff::default();
+
+    let old_types = types_by_fqn(old);
+    let new_types = types_by_fqn(new);
+
+    for (fqn, old_ty) in &old_types {
+        match new_types.get(fqn) {
+            Some(new_ty) => diff_type(&mut diff, fqn, old, old_ty, new, new_ty),
+            None => diff.push(AbiChangeKind::TypeRemoved { ty: fqn.clone() }, Breaking),
+        }
+    }
+    for fqn in new_types.keys() {
+        if !old_types.contains_key(fqn) {
+            diff.push(AbiChangeKind::TypeAdded { ty: fqn.clone() }, Compatible);
+        }
+    }
+
+    let old_fns = fns_by_fqn(old);
+    let new_fns = fns_by_fqn(new);
+
+    for (fqn, old_fn) in &old_fns {
+        match new_fns.get(fqn) {
+            Some(new_fn) => diff_fn(&mut diff, fqn, old, old_fn, new, new_fn),
+            None => diff.push(
+                AbiChangeKind::FnRemoved {
+                    function: fqn.clone(),
+                },
+                Breaking,
+            ),
+        }
+    }
+    for fqn in new_fns.keys() {
+        if !old_fns.contains_key(fqn) {
+            diff.push(
+                AbiChangeKind::FnAdded {
+                    function: fqn.clone(),
+                },
+                Compatible,
+            );
+        }
+    }
+
+    diff
+}
+
+fn types_by_fqn(abi: &Abi) -> BTreeMap<String, Arc<AbiType>> {
+    let mut types = BTreeMap::new();
+    for ty in abi.types.iter() {
+        types.entry(ty.named_fqn(abi)).or_insert_with(|| ty.clone());
+    }
+    types
+}
+
+fn fns_by_fqn(abi: &Abi) -> BTreeMap<String, &AbiFn> {
+    let mut functions = BTreeMap::new();
+    for function in abi.functions.iter() {
+        functions.entry(function.named_fqn(abi)).or_insert(function);
+    }
+    functions
+}
+
+fn attr_type_fqn(attr: &AbiAttr, abi: &Abi) -> String {
+    match attr.prog_type(abi) {
+        Some(ty) => ty.named_fqn(abi),
+        None => format!("Type#{}", attr.prog_type_offset),
+    }
+}
+
+fn diff_type(
+    diff: &mut AbiDiff,
+    fqn: &str,
+    old: &Abi,
+    old_ty: &AbiType,
+    new: &Abi,
+    new_ty: &AbiType,
+) {
+    use Compatibility::*;
+
+    let old_attrs = old_ty.attrs.as_deref().unwrap_or_default();
+    let new_attrs = new_ty.attrs.as_deref().unwrap_or_default();
+    let new_by_name: BTreeMap<&str, (usize, &AbiAttr)> = new_attrs
+        .iter()
+        .enumerate()
+        .map(|(offset, attr)| (&new.symbols[attr.name], (offset, attr)))
+        .collect();
+    let old_by_name: BTreeMap<&str, &AbiAttr> = old_attrs
+        .iter()
+        .map(|attr| (&old.symbols[attr.name], attr))
+        .collect();
+
+    if old_ty.is_enum || new_ty.is_enum {
+        for name in old_by_name.keys() {
+            if !new_by_name.contains_key(name) {
+                diff.push(
+                    AbiChangeKind::EnumVariantRemoved {
+                        ty: fqn.to_string(),
+                        variant: name.to_string(),
+                    },
+                    Breaking,
+                );
+            }
+        }
+        for name in new_by_name.keys() {
+            if !old_by_name.contains_key(name) {
+                diff.push(
+                    AbiChangeKind::EnumVariantAdded {
+                        ty: fqn.to_string(),
+                        variant: name.to_string(),
+                    },
+                    Compatible,
+                );
+            }
+        }
+        return;
+    }
+
+    // an attribute only moves when its order relative to the other attributes of both ABIs
+    // changes, not when another attribute is inserted or removed before it
+    let common: Vec<(usize, usize)> = old_attrs
+        .iter()
+        .enumerate()
+        .filter_map(|(old_offset, old_attr)| {
+            let (new_offset, _) = new_by_name.get(&old.symbols[old_attr.name])?;
+            Some((old_offset, *new_offset))
+        })
+        .collect();
+    let in_order = longest_increasing(&common.iter().map(|(_, new)| *new).collect::<Vec<_>>());
+    let moved: BTreeSet<usize> = common
+        .iter()
+        .zip(in_order)
+        .filter(|(_, in_order)| !in_order)
+        .map(|((old_offset, _), _)| *old_offset)
+        .collect();
+
+    for (old_offset, old_attr) in old_attrs.iter().enumerate() {
+        let name = &old.symbols[old_attr.name];
+        let Some((new_offset, new_attr)) = new_by_name.get(name) else {
+            diff.push(
+                AbiChangeKind::AttrRemoved {
+                    ty: fqn.to_string(),
+                    attr: name.to_string(),
+                },
+                Breaking,
+            );
+            continue;
+        };
+
+        let old_attr_ty = attr_type_fqn(old_attr, old);
+        let new_attr_ty = attr_type_fqn(new_attr, new);
+        if old_attr_ty != new_attr_ty {
+            diff.push(
+                AbiChangeKind::AttrTypeChanged {
+                    ty: fqn.to_string(),
+                    attr: name.to_string(),
+                    old: old_attr_ty,
+                    new: new_attr_ty,
+                },
+                Breaking,
+            );
+        }
+        // readers do not expect `null` and writers may not provide a value anymore: both ways break
+        if old_attr.nullable != new_attr.nullable {
+            diff.push(
+                AbiChangeKind::AttrNullabilityChanged {
+                    ty: fqn.to_string(),
+                    attr: name.to_string(),
+                    nullable: new_attr.nullable,
+                },
+                Breaking,
+            );
+        }
+        // objects are encoded positionally
+        if moved.contains(&old_offset) {
+            diff.push(
+                AbiChangeKind::AttrMoved {
+                    ty: fqn.to_string(),
+                    attr: name.to_string(),
+                    old: old_offset,
+                    new: *new_offset,
+                },
+                Breaking,
+            );
+        }
+    }
+
+    for new_attr in new_attrs {
+        let name = &new.symbols[new_attr.name];
+        if !old_by_name.contains_key(name) {
+            diff.push(
+                AbiChangeKind::AttrAdded {
+                    ty: fqn.to_string(),
+                    attr: name.to_string(),
+                    nullable: new_attr.nullable,
+                },
+                if new_attr.nullable {
+                    Compatible
+                } else {
+                    Breaking
+                },
+            );
+        }
+    }
+}
+
+/// Returns, for each of the `offsets`, whether it belongs to the first longest increasing
+/// subsequence of `offsets`
+fn longest_increasing(offsets: &[usize]) -> Vec<bool> {
+    // the length of the longest subsequence ending at each offset, and the offset before it
+    let mut lengths = vec![1; offsets.len()];
+    let mut previous = vec![None; offsets.len()];
+    for i in 0..offsets.len() {
+        for j in 0..i {
+            if offsets[j] < offsets[i] && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut in_order = vec![false; offsets.len()];
+    let mut last = lengths
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, length)| **length)
+        .map(|(i, _)| i);
+    while let Some(i) = last {
+        in_order[i] = true;
+        last = previous[i];
+    }
+    in_order
+}
+
+fn diff_fn(diff: &mut AbiDiff, fqn: &str, old: &Abi, old_fn: &AbiFn, new: &Abi, new_fn: &AbiFn) {
+    use Compatibility::*;
+
+    // parameters are sent positionally, so they are compared by position
+    for (old_param, new_param) in old_fn.params.iter().zip(new_fn.params.iter()) {
+        let param = new.symbols[new_param.name].to_string();
+        let old_param_ty = old_param.r#type.named_fqn(old);
+        let new_param_ty = new_param.r#type.named_fqn(new);
+        if old_param_ty != new_param_ty {
+            diff.push(
+                AbiChangeKind::ParamTypeChanged {
+                    function: fqn.to_string(),
+                    param: param.clone(),
+                    old: old_param_ty,
+                    new: new_param_ty,
+                },
+                Breaking,
+            );
+        }
+        if old_param.nullable != new_param.nullable {
+            diff.push(
+                AbiChangeKind::ParamNullabilityChanged {
+                    function: fqn.to_string(),
+                    param,
+                    nullable: new_param.nullable,
+                },
+                if new_param.nullable {
+                    Compatible
+                } else {
+                    Breaking
+                },
+            );
+        }
+    }
+    for old_param in old_fn.params.iter().skip(new_fn.params.len()) {
+        diff.push(
+            AbiChangeKind::ParamRemoved {
+                function: fqn.to_string(),
+                param: old.symbols[old_param.name].to_string(),
+            },
+            Breaking,
+        );
+    }
+    for new_param in new_fn.params.iter().skip(old_fn.params.len()) {
+        diff.push(
+            AbiChangeKind::ParamAdded {
+                function: fqn.to_string(),
+                param: new.symbols[new_param.name].to_string(),
+            },
+            Breaking,
+        );
+    }
+
+    let old_return_ty = old_fn.return_type.named_fqn(old);
+    let new_return_ty = new_fn.return_type.named_fqn(new);
+    if old_return_ty != new_return_ty {
+        diff.push(
+            AbiChangeKind::ReturnTypeChanged {
+                function: fqn.to_string(),
+                old: old_return_ty,
+                new: new_return_ty,
+            },
+            Breaking,
+        );
+    }
+    if old_fn.return_nullable != new_fn.return_nullable {
+        diff.push(
+            AbiChangeKind::ReturnNullabilityChanged {
+                function: fqn.to_string(),
+                nullable: new_fn.return_nullable,
+            },
+            if new_fn.return_nullable {
+                Breaking
+            } else {
+                Compatible
+            },
+        );
+    }
+}
+
+impl std::fmt::Display for AbiChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use AbiChangeKind::*;
+
+        let nullability = |nullable: &bool| {
+            if *nullable {
+                "nullable"
+            } else {
+                "non-nullable"
+            }
+        };
+
+        match self {
+            TypeAdded { ty } => write!(f, "type '{ty}' added"),
+            TypeRemoved { ty } => write!(f, "type '{ty}' removed"),
+            EnumVariantAdded { ty, variant } => write!(f, "enum variant '{ty}::{variant}' added"),
+            EnumVariantRemoved { ty, variant } => {
+                write!(f, "enum variant '{ty}::{variant}' removed")
+            }
+            AttrAdded { ty, attr, nullable } => write!(
+                f,
+                "attribute '{ty}.{attr}' added ({})",
+                nullability(nullable)
+            ),
+            AttrRemoved { ty, attr } => write!(f, "attribute '{ty}.{attr}' removed"),
+            AttrTypeChanged { ty, attr, old, new } => write!(
+                f,
+                "attribute '{ty}.{attr}' type changed from '{old}' to '{new}'"
+            ),
+            AttrNullabilityChanged { ty, attr, nullable } => write!(
+                f,
+                "attribute '{ty}.{attr}' is now {}",
+                nullability(nullable)
+            ),
+            AttrMoved { ty, attr, old, new } => write!(
+                f,
+                "attribute '{ty}.{attr}' moved from position {old} to {new}"
+            ),
+            FnAdded { function } => write!(f, "function '{function}' added"),
+            FnRemoved { function } => write!(f, "function '{function}' removed"),
+            ParamAdded { function, param } => {
+                write!(f, "parameter '{param}' added to '{function}'")
+            }
+            ParamRemoved { function, param } => {
+                write!(f, "parameter '{param}' removed from '{function}'")
+            }
+            ParamTypeChanged {
+                function,
+                param,
+                old,
+                new,
+            } => write!(
+                f,
+                "parameter '{param}' of '{function}' type changed from '{old}' to '{new}'"
+            ),
+            ParamNullabilityChanged {
+                function,
+                param,
+                nullable,
+            } => write!(
+                f,
+                "parameter '{param}' of '{function}' is now {}",
+                nullability(nullable)
+            ),
+            ReturnTypeChanged { function, old, new } => write!(
+                f,
+                "return type of '{function}' changed from '{old}' to '{new}'"
+            ),
+            ReturnNullabilityChanged { function, nullable } => write!(
+                f,
+                "return type of '{function}' is now {}",
+                nullability(nullable)
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for AbiChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.compatibility {
+            Compatibility::Compatible => write!(f, "[compatible] {}", self.kind),
+            Compatibility::Breaking => write!(f, "[breaking]   {}", self.kind),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::{attr, native_abi, project_abi, rebuild_abi};
+    use crate::abi::AbiFunctions;
+    use crate::primitive;
+
+    fn change(kind: AbiChangeKind, compatibility: Compatibility) -> AbiChange {
+        AbiChange {
+            kind,
+            compatibility,
+        }
+    }
+
+    #[test]
+    fn diff_by_name() {
+        let old = project_abi();
+        assert!(diff(&old, &old).is_empty());
+
+        // 'Person.age' becomes a String and 'Status::Inactive' is removed
+        let new = rebuild_abi(&old, &[], |name, attrs| match name {
+            "Person" => {
+                attrs[1].prog_type_offset = 1;
+                attrs[1].sbi_type = primitive::OBJECT;
+            }
+            "Status" => {
+                attrs.pop();
+            }
+            _ => (),
+        });
+
+        let changes = diff(&old, &new);
+        assert!(changes.is_breaking());
+        assert_eq!(
+            changes.changes,
+            vec![
+                AbiChange {
+                    kind: AbiChangeKind::AttrTypeChanged {
+                        ty: "project::Person".to_string(),
+                        attr: "age".to_string(),
+                        old: "core::int".to_string(),
+                        new: "core::String".to_string(),
+                    },
+                    compatibility: Compatibility::Breaking,
+                },
+                AbiChange {
+                    kind: AbiChangeKind::EnumVariantRemoved {
+                        ty: "project::Status".to_string(),
+                        variant: "Inactive".to_string(),
+                    },
+                    compatibility: Compatibility::Breaking,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_added_and_removed_types() {
+        let old = project_abi();
+        let new = native_abi();
+
+        let added = diff(&old, &new);
+        assert!(!added.is_breaking());
+        assert_eq!(
+            added.changes,
+            ["Buffer", "Error", "Set", "Table", "Tensor", "Tuple"]
+                .map(|name| change(
+                    AbiChangeKind::TypeAdded {
+                        ty: format!("core::{name}")
+                    },
+                    Compatibility::Compatible
+                ))
+                .to_vec()
+        );
+
+        let removed = diff(&new, &old);
+        assert_eq!(removed.breaking().count(), 6);
+        assert!(removed
+            .changes
+            .iter()
+            .all(|change| matches!(change.kind, AbiChangeKind::TypeRemoved { .. })));
+    }
+
+    #[test]
+    fn diff_added_removed_and_moved_attrs() {
+        let old = project_abi();
+        // Person { email: String?; name: String; age: int; }
+        let new = rebuild_abi(&old, &["email"], |name, attrs| {
+            if name == "Person" {
+                let mut email = attr(17, 1, 0, primitive::OBJECT);
+                email.nullable = true;
+                attrs.insert(0, email);
+            }
+        });
+
+        assert_eq!(
+            diff(&old, &new).changes,
+            vec![change(
+                AbiChangeKind::AttrAdded {
+                    ty: "project::Person".to_string(),
+                    attr: "email".to_string(),
+                    nullable: true,
+                },
+                Compatibility::Compatible
+            )]
+        );
+        assert_eq!(
+            diff(&new, &old).changes,
+            vec![change(
+                AbiChangeKind::AttrRemoved {
+                    ty: "project::Person".to_string(),
+                    attr: "email".to_string(),
+                },
+                Compatibility::Breaking
+            )]
+        );
+
+        // Person { age: int; name: String; }
+        let swapped = rebuild_abi(&old, &[], |name, attrs| {
+            if name == "Person" {
+                attrs.reverse();
+            }
+        });
+        assert_eq!(
+            diff(&old, &swapped).changes,
+            vec![change(
+                AbiChangeKind::AttrMoved {
+                    ty: "project::Person".to_string(),
+                    attr: "age".to_string(),
+                    old: 1,
+                    new: 0,
+                },
+                Compatibility::Breaking
+            )]
+        );
+    }
+
+    #[test]
+    fn diff_fn_signatures() {
+        let old = project_abi();
+        // fn add(a: int?): String?
+        let mut functions = old.functions.to_vec();
+        let add = &mut functions[0];
+        add.params.pop();
+        add.params[0].nullable = true;
+        add.return_type = old.types[1u32].clone();
+        add.return_nullable = true;
+        let new = Abi::from_parts(
+            old.headers,
+            old.symbols.clone(),
+            old.types.clone(),
+            AbiFunctions::new(functions),
+            None,
+        )
+        .unwrap();
+
+        let function = || "project::add".to_string();
+        let return_type_changed = |old: &str, new: &str| AbiChangeKind::ReturnTypeChanged {
+            function: function(),
+            old: old.to_string(),
+            new: new.to_string(),
+        };
+        assert_eq!(
+            diff(&old, &new).changes,
+            vec![
+                change(
+                    AbiChangeKind::ParamNullabilityChanged {
+                        function: function(),
+                        param: "a".to_string(),
+                        nullable: true,
+                    },
+                    Compatibility::Compatible
+                ),
+                change(
+                    AbiChangeKind::ParamRemoved {
+                        function: function(),
+                        param: "b".to_string(),
+                    },
+                    Compatibility::Breaking
+                ),
+                change(
+                    return_type_changed("core::int", "core::String"),
+                    Compatibility::Breaking
+                ),
+                change(
+                    AbiChangeKind::ReturnNullabilityChanged {
+                        function: function(),
+                        nullable: true,
+                    },
+                    Compatibility::Breaking
+                ),
+            ]
+        );
+        assert_eq!(
+            diff(&new, &old).changes,
+            vec![
+                change(
+                    AbiChangeKind::ParamNullabilityChanged {
+                        function: function(),
+                        param: "a".to_string(),
+                        nullable: false,
+                    },
+                    Compatibility::Breaking
+                ),
+                change(
+                    AbiChangeKind::ParamAdded {
+                        function: function(),
+                        param: "b".to_string(),
+                    },
+                    Compatibility::Breaking
+                ),
+                change(
+                    return_type_changed("core::String", "core::int"),
+                    Compatibility::Breaking
+                ),
+                change(
+                    AbiChangeKind::ReturnNullabilityChanged {
+                        function: function(),
+                        nullable: false,
+                    },
+                    Compatibility::Compatible
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Name-resolved JSON representation of an [`Abi`].
+//!
+//! Unlike the `Serialize` impl of [`Abi`] which emits raw symbol ids, every reference to a symbol
+//! or a type is resolved to its name. The document has the following shape:
+//!
+//! ```json
+//! {
+//!   "headers": { "protocol": 1, "magic": 4919, "version": 42, "crc": 3735928559 },
+//!   "types": [
+//!     {
+//!       "fqn": "project::Person",
+//!       "module": "project",
+//!       "name": "Person",
+//!       "lib": "std",
+//!       "is_native": false,
+//!       "is_abstract": false,
+//!       "is_enum": false,
+//!       "is_masked": false,
+//!       "attrs": [
+//!         { "name": "name", "type": "core::String", "nullable": false },
+//!         { "name": "age", "type": "core::int", "nullable": false }
+//!       ]
+//!     },
+//!     {
+//!       "fqn": "project::Status",
+//!       "module": "project",
+//!       "name": "Status",
+//!       "lib": "std",
+//!       "is_native": false,
+//!       "is_abstract": false,
+//!       "is_enum": true,
+//!       "is_masked": false,
+//!       "variants": ["Active", "Inactive"]
+//!     }
+//!   ],
+//!   "functions": [
+//!     {
+//!       "fqn": "project::add",
+//!       "module": "project",
+//!       "type": null,
+//!       "name": "add",
+//!       "lib": "std",
+//!       "params": [
+//!         { "name": "a", "type": "core::int", "nullable": false },
+//!         { "name": "b", "type": "core::int", "nullable": false }
+//!       ],
+//!       "return_type": "core::int",
+//!       "return_nullable": false,
+//!       "is_task": false
+//!     }
+//!   ],
+//!   "modvars": [
+//!     { "fqn": "project::value", "module": "project", "name": "value", "type": "core::int", "nullable": true }
+//!   ]
+//! }
+//! ```
+//!
+//! - enum types have `"variants"` instead of `"attrs"`
+//! - `"type"` of a function is the name of the type for methods, `null` otherwise
+//! - fields are never removed nor renamed, new fields may be added
+
+use serde::{Serialize, Serializer};
+
+use crate::abi::{Abi, AbiAttr, AbiFn, AbiHeaders, AbiType};
+
+/// Serializes the wrapped [`Abi`] using names instead of symbol ids, see the [module docs](self)
+pub struct NamedAbi<'abi>(pub &'abi Abi);
+
+impl Abi {
+    /// Returns a serializable view of this ABI that uses names instead of symbol ids
+    #[inline]
+    pub fn named(&self) -> NamedAbi<'_> {
+        NamedAbi(self)
+    }
+}
+
+#[derive(Serialize)]
+struct Document<'abi> {
+    headers: &'abi AbiHeaders,
+    types: Vec<NamedType<'abi>>,
+    functions: Vec<NamedFn<'abi>>,
+    modvars: Vec<NamedModVar<'abi>>,
+}
+
+#[derive(Serialize)]
+struct NamedType<'abi> {
+    fqn: String,
+    module: &'abi str,
+    name: &'abi str,
+    lib: &'abi str,
+    is_native: bool,
+    is_abstract: bool,
+    is_enum: bool,
+    is_masked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attrs: Option<Vec<NamedAttr<'abi>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variants: Option<Vec<&'abi str>>,
+}
+
+#[derive(Serialize)]
+struct NamedAttr<'abi> {
+    name: &'abi str,
+    #[serde(rename = "type")]
+    ty: String,
+    nullable: bool,
+}
+
+#[derive(Serialize)]
+struct NamedFn<'abi> {
+    fqn: String,
+    module: &'abi str,
+    #[serde(rename = "type")]
+    ty: Option<&'abi str>,
+    name: &'abi str,
+    lib: &'abi str,
+    params: Vec<NamedAttr<'abi>>,
+    return_type: String,
+    return_nullable: bool,
+    is_task: bool,
+}
+
+#[derive(Serialize)]
+struct NamedModVar<'abi> {
+    fqn: String,
+    module: &'abi str,
+    name: &'abi str,
+    #[serde(rename = "type")]
+    ty: String,
+    nullable: bool,
+}
+
+impl<'abi> NamedType<'abi> {
+    fn new(ty: &'abi AbiType, abi: &'abi Abi) -> Self {
+        let attrs = ty.attrs.as_deref().unwrap_or_default();
+        let (attrs, variants) = if ty.is_enum {
+            let variants = attrs.iter().map(|attr| &abi.symbols[attr.name]).collect();
+            (None, Some(variants))
+        } else {
+            let attrs = attrs.iter().map(|attr| NamedAttr::new(attr, abi)).collect();
+            (Some(attrs), None)
+        };
+
+        Self {
+            fqn: ty.named_fqn(abi),
+            module: &abi.symbols[ty.module],
+            name: &abi.symbols[ty.name],
+            lib: &abi.symbols[ty.lib_name],
+            is_native: ty.is_native,
+            is_abstract: ty.is_abstract,
+            is_enum: ty.is_enum,
+            is_masked: ty.is_masked,
+            attrs,
+            variants,
+        }
+    }
+}
+
+impl<'abi> NamedAttr<'abi> {
+    fn new(attr: &'abi AbiAttr, abi: &'abi Abi) -> Self {
+        Self {
+            name: &abi.symbols[attr.name],
+            ty: type_name(attr.prog_type_offset, abi),
+            nullable: attr.nullable,
+        }
+    }
+}
+
+impl<'abi> NamedFn<'abi> {
+    fn new(function: &'abi AbiFn, abi: &'abi Abi) -> Self {
+        Self {
+            fqn: function.named_fqn(abi),
+            module: &abi.symbols[function.module],
+            ty: function.r#type.map(|ty| &abi.symbols[ty]),
+            name: &abi.symbols[function.name],
+            lib: &abi.symbols[function.lib_name],
+            params: function
+                .params
+                .iter()
+                .map(|param| NamedAttr {
+                    name: &abi.symbols[param.name],
+                    ty: param.r#type.named_fqn(abi),
+                    nullable: param.nullable,
+                })
+                .collect(),
+            return_type: function.return_type.named_fqn(abi),
+            return_nullable: function.return_nullable,
+            is_task: function.is_task,
+        }
+    }
+}
+
+fn type_name(offset: u32, abi: &Abi) -> String {
+    match abi.types.get(offset) {
+        Some(ty) => ty.named_fqn(abi),
+        None => format!("Type#{offset}"),
+    }
+}
+
+impl Serialize for NamedAbi<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let abi = self.0;
+
+        let types = abi
+            .types
+            .iter()
+            // the module variables holder is exposed through "modvars"
+            .filter(|ty| !(abi.symbols[ty.module].is_empty() && &abi.symbols[ty.name] == "$$$root"))
+            .map(|ty| NamedType::new(ty, abi))
+            .collect();
+        let functions = abi
+            .functions
+            .iter()
+            .map(|function| NamedFn::new(function, abi))
+            .collect();
+        let modvars = abi
+            .get_modvars()
+            .into_iter()
+            .map(|var| {
+                let module = &abi.symbols[var.module];
+                let name = &abi.symbols[var.name];
+                NamedModVar {
+                    fqn: format!("{module}::{name}"),
+                    module,
+                    name,
+                    ty: type_name(var.ty, abi),
+                    nullable: var.nullable,
+                }
+            })
+            .collect();
+
+        Document {
+            headers: &abi.headers,
+            types,
+            functions,
+            modvars,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn named_abi_json() {
+        let abi = project_abi();
+        let json = serde_json::to_value(abi.named()).unwrap();
+
+        assert_eq!(json["types"][4]["fqn"], "project::Person");
+        assert_eq!(
+            json["types"][4]["attrs"][1],
+            serde_json::json!({ "name": "age", "type": "core::int", "nullable": false })
+        );
+        assert_eq!(
+            json["types"][5]["variants"],
+            serde_json::json!(["Active", "Inactive"])
+        );
+        assert_eq!(json["functions"][0]["fqn"], "project::add");
+        assert_eq!(json["functions"][0]["params"][0]["type"], "core::int");
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use byteorder::{ReadBytesExt, LE};
+use memmap2::Mmap;
+
+use super::*;
+use crate::error::{Error, Result};
+
+/// An ABI file loaded using a memory-map.
+///
+/// Symbols are borrowed straight from the mapped bytes, and the types and functions
+/// sections, as well as their lookup tables, are only decoded on first access.
+pub struct MappedAbi {
+    data: Arc<Mmap>,
+    pub headers: AbiHeaders,
+    pub symbols: AbiSymbols,
+    /// offset of the types section in `data`
+    types_offset: usize,
+    /// offset of the functions section in `data`
+    functions_offset: usize,
+    types: OnceLock<AbiTypes>,
+    functions: OnceLock<AbiFunctions>,
+    types_index: OnceLock<TypesIndex>,
+    functions_index: OnceLock<FunctionsIndex>,
+}
+
+impl MappedAbi {
+    /// Memory-maps the ABI file at `path` and reads its headers and symbols.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified nor truncated for as long as the `MappedAbi`, or the
+    /// [`Abi`] created from it, is alive: the symbols are checked to be valid UTF-8 once and
+    /// then read straight from the map, so any change is undefined behavior (or a `SIGBUS`).
+    ///
+    /// GreyCat only (re)writes `gcdata/store/abi` when starting, which makes this fine for
+    /// short-lived processes, but long-lived services should rather read the file using
+    /// [`Abi::new`].
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        // SAFETY: upheld by the caller, see above
+        let mmap = unsafe { Mmap::map(&file) }?;
+
+        Self::from_mmap(Arc::new(mmap))
+    }
+
+    fn from_mmap(data: Arc<Mmap>) -> Result<Self> {
+        let headers = (&data[..]).read_abi_headers()?;
+        let (symbols, types_offset) = AbiSymbols::from_shared(data.clone(), 16)?;
+
+        let mut section = data.get(types_offset..).unwrap_or_default();
+        let types_size = section.read_u64::<LE>()? as usize;
+        let functions_offset = types_offset + 8 + types_size;
+        if functions_offset > data.len() {
+            return Err(Error::InvalidAbi {
+                reason: format!("types section overflows the file ({types_size} bytes)"),
+            });
+        }
+
+        Ok(Self {
+            data,
+            headers,
+            symbols,
+            types_offset,
+            functions_offset,
+            types: OnceLock::new(),
+            functions: OnceLock::new(),
+            types_index: OnceLock::new(),
+            functions_index: OnceLock::new(),
+        })
+    }
+
+    /// Returns the types, decoding them on first access
+    pub fn types(&self) -> Result<&AbiTypes> {
+        if let Some(types) = self.types.get() {
+            return Ok(types);
+        }
+
+        let mut bytes = &self.data[self.types_offset..self.functions_offset];
+        let types = bytes
+            .read_abi_types(&self.symbols)
+            .map_err(|err| err.at((self.functions_offset - bytes.len()) as u64))?;
+        if !bytes.is_empty() {
+            return Err(Error::InvalidAbi {
+                reason: format!("{} trailing bytes after the types", bytes.len()),
+            });
+        }
+        // another thread might have won the race, the types are the same anyway
+        Ok(self.types.get_or_init(|| types))
+    }
+
+    /// Returns the functions, decoding them (and the types they refer to) on first access
+    pub fn functions(&self) -> Result<&AbiFunctions> {
+        if let Some(functions) = self.functions.get() {
+            return Ok(functions);
+        }
+
+        let types = self.types()?;
+        let mut bytes = &self.data[self.functions_offset..];
+        let functions = bytes
+            .read_abi_functions(&self.symbols, types)
+            .map_err(|err| err.at((self.data.len() - bytes.len()) as u64))?;
+        Ok(self.functions.get_or_init(|| functions))
+    }
+
+    /// Returns the type named `"<module>::<name>"`, decoding the types on first access
+    pub fn get_type_by_fqn(&self, fqn: &str) -> Result<Option<Arc<AbiType>>> {
+        let types = self.types()?;
+        let index = self
+            .types_index
+            .get_or_init(|| TypesIndex::new(&self.symbols, types));
+        Ok(index.get(fqn).and_then(|offset| types.get(offset)))
+    }
+
+    /// Returns the function named `"<module>::<name>"` or, for type methods, `"<module>::<type>::<name>"`,
+    /// decoding the types and functions on first access
+    pub fn get_fn_by_fqn(&self, fqn: &str) -> Result<Option<&AbiFn>> {
+        let functions = self.functions()?;
+        let index = self
+            .functions_index
+            .get_or_init(|| FunctionsIndex::new(&self.symbols, functions));
+        Ok(index
+            .get(fqn)
+            .and_then(|offset| functions.get(offset as usize)))
+    }
+
+    /// Decodes whatever is left to decode and creates the `Abi`, the symbols are still borrowed from the map.
+    ///
+    /// The lookup tables already built are kept, the others are built on first use by the `Abi`.
+    pub fn into_abi(self, libraries: Option<Vec<Box<dyn Library>>>) -> Result<Abi> {
+        self.functions()?;
+        let Self {
+            headers,
+            symbols,
+            types,
+            functions,
+            types_index,
+            functions_index,
+            ..
+        } = self;
+        let types = types
+            .into_inner()
+            .expect("types are decoded along with functions");
+        let functions = functions.into_inner().expect("functions are decoded");
+
+        Abi::from_mapped_parts(
+            headers,
+            symbols,
+            types,
+            functions,
+            (types_index, functions_index),
+            libraries,
+        )
+    }
+}
+
+impl std::fmt::Debug for MappedAbi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedAbi")
+            .field("headers", &self.headers)
+            .field("symbols", &self.symbols.len())
+            .field("types", &self.types.get().map(|types| types.len()))
+            .field(
+                "functions",
+                &self.functions.get().map(|functions| functions.len()),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn mapped_abi_lazy_sections() {
+        let abi = project_abi();
+        let mut bytes = Vec::new();
+        abi.write_to(&mut bytes, &abi).unwrap();
+
+        let path = std::env::temp_dir().join(format!("greycat-sdk-mapped-{}", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        // SAFETY: the file is only removed once mapped, which keeps its content alive
+        let mapped = unsafe { MappedAbi::open(&path) }.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(mapped.headers, abi.headers);
+        assert_eq!(mapped.symbols.get("Person"), Some(8));
+        assert_eq!(&mapped.symbols[10u32], "age");
+        assert!(mapped.types.get().is_none());
+        assert!(mapped.functions.get().is_none());
+
+        let person = mapped.get_type_by_fqn("project::Person").unwrap().unwrap();
+        assert_eq!(person.offset, 4);
+        assert!(mapped.types_index.get().is_some());
+        assert!(mapped.functions.get().is_none());
+        assert!(mapped.get_type_by_fqn("project::Nope").unwrap().is_none());
+
+        let add = mapped.get_fn_by_fqn("project::add").unwrap().unwrap();
+        assert_eq!(&mapped.symbols[add.name], "add");
+        assert!(mapped
+            .get_fn_by_fqn("project::Person::add")
+            .unwrap()
+            .is_none());
+        assert_eq!(mapped.types.get().map(|types| types.len()), Some(6));
+
+        let read = mapped.into_abi(None).unwrap();
+        assert!(read.index.types.get().is_some());
+        assert!(read.get_type_by_fqn("project::Person").is_some());
+        assert!(read.get_fn_by_fqn("project::add").is_some());
+
+        // the symbols section must end where its size says
+        let mut symbols = bytes[..16].to_vec();
+        abi.symbols.write_to(&mut symbols, &abi).unwrap();
+        symbols[16] += 1;
+        symbols.push(0);
+        assert!(matches!(
+            AbiSymbols::from_shared(Arc::new(symbols.clone()), 16),
+            Err(Error::InvalidAbi { .. })
+        ));
+        // and cannot claim more symbols than it has bytes
+        symbols[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            AbiSymbols::from_shared(Arc::new(symbols), 16),
+            Err(Error::UnexpectedEof { .. })
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use super::{Abi, AbiFn, AbiType};
+
+/// A view over everything an [`Abi`] defines in one module, with resolved names
+#[derive(Debug, Clone)]
+pub struct AbiModule<'abi> {
+    pub name: &'abi str,
+    /// types that are not enums
+    pub types: Vec<ModuleType<'abi>>,
+    pub enums: Vec<ModuleType<'abi>>,
+    /// module-level functions
+    pub functions: Vec<ModuleFn<'abi>>,
+    /// functions defined on the types of the module
+    pub methods: Vec<ModuleFn<'abi>>,
+    pub vars: Vec<ModuleVar<'abi>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleType<'abi> {
+    pub name: &'abi str,
+    pub ty: Arc<AbiType>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleFn<'abi> {
+    pub name: &'abi str,
+    /// the name of the type, for methods
+    pub ty: Option<&'abi str>,
+    pub function: &'abi AbiFn,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleVar<'abi> {
+    pub name: &'abi str,
+    pub ty: Arc<AbiType>,
+    pub nullable: bool,
+}
+
+impl<'abi> AbiModule<'abi> {
+    fn new(name: &'abi str) -> Self {
+        Self {
+            name,
+            types: Vec::new(),
+            enums: Vec::new(),
+            functions: Vec::new(),
+            methods: Vec::new(),
+            vars: Vec::new(),
+        }
+    }
+}
+
+impl Abi {
+    /// Returns the modules of the ABI, sorted by name
+    pub fn modules(&self) -> Vec<AbiModule<'_>> {
+        let mut modules: Vec<_> = self.collect_modules(None).into_values().collect();
+        modules.sort_unstable_by_key(|module| module.name);
+        modules
+    }
+
+    /// Returns the module named `name`, if the ABI defines anything in it
+    pub fn module(&self, name: &str) -> Option<AbiModule<'_>> {
+        let id = self.symbols.get(name)?;
+        self.collect_modules(Some(id)).remove(&id)
+    }
+
+    /// Collects the modules by symbol id, or only the module `only` if given
+    fn collect_modules(&self, only: Option<u32>) -> BTreeMap<u32, AbiModule<'_>> {
+        let mut modules: BTreeMap<u32, AbiModule<'_>> = BTreeMap::new();
+        let wanted = |module: u32| only.is_none_or(|only| only == module);
+
+        for ty in self.types.iter().filter(|ty| wanted(ty.module)) {
+            let name = &self.symbols[ty.name];
+            if ty.module == 0 && name == "$$$root" {
+                // the module variables holder, they are listed in `vars`
+                continue;
+            }
+            let entry = ModuleType {
+                name,
+                ty: Arc::clone(ty),
+            };
+            if ty.is_enum {
+                self.module_entry(&mut modules, ty.module).enums.push(entry);
+            } else {
+                self.module_entry(&mut modules, ty.module).types.push(entry);
+            }
+        }
+
+        for function in self.functions.iter().filter(|f| wanted(f.module)) {
+            let entry = ModuleFn {
+                name: &self.symbols[function.name],
+                ty: function.r#type.map(|ty| &self.symbols[ty]),
+                function,
+            };
+            if entry.ty.is_some() {
+                self.module_entry(&mut modules, function.module)
+                    .methods
+                    .push(entry);
+            } else {
+                self.module_entry(&mut modules, function.module)
+                    .functions
+                    .push(entry);
+            }
+        }
+
+        for var in self
+            .get_modvars()
+            .into_iter()
+            .filter(|var| wanted(var.module))
+        {
+            if let Some(ty) = self.types.get(var.ty) {
+                self.module_entry(&mut modules, var.module)
+                    .vars
+                    .push(ModuleVar {
+                        name: &self.symbols[var.name],
+                        ty,
+                        nullable: var.nullable,
+                    });
+            }
+        }
+
+        modules
+    }
+
+    fn module_entry<'a, 'abi>(
+        &'abi self,
+        modules: &'a mut BTreeMap<u32, AbiModule<'abi>>,
+        id: u32,
+    ) -> &'a mut AbiModule<'abi> {
+        modules
+            .entry(id)
+            .or_insert_with(|| AbiModule::new(&self.symbols[id]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn modules_by_name() {
+        let abi = project_abi();
+        let modules = abi.modules();
+        let names: Vec<_> = modules.iter().map(|module| module.name).collect();
+        assert_eq!(names, ["core", "project"]);
+
+        let project = abi.module("project").unwrap();
+        let types: Vec<_> = project.types.iter().map(|ty| ty.name).collect();
+        let enums: Vec<_> = project.enums.iter().map(|ty| ty.name).collect();
+        let functions: Vec<_> = project.functions.iter().map(|f| f.name).collect();
+        assert_eq!(types, ["Person"]);
+        assert_eq!(enums, ["Status"]);
+        assert_eq!(functions, ["add"]);
+        assert!(project.methods.is_empty());
+        assert!(abi.module("unknown").is_none());
+        // a symbol that is not a module
+        assert!(abi.module("Person").is_none());
+
+        let core = abi.module("core").unwrap();
+        let types: Vec<_> = core.types.iter().map(|ty| ty.name).collect();
+        assert_eq!(types, ["int", "String", "Array", "Map"]);
+        assert!(core.functions.is_empty() && core.enums.is_empty());
+    }
+}
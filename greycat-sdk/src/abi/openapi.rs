@@ -0,0 +1,149 @@
+//! [OpenAPI 3.1](https://spec.openapis.org/oas/v3.1.0) document of the functions of an [`Abi`].
+//!
+//! Each function is a `POST /<module>::<fn>` (`/<module>::<type>::<fn>` for methods) whose JSON
+//! request body is the array of its arguments, in the order of its parameters, and whose response
+//! is its return value, both in [typed JSON](crate::typed_json).
+//!
+//! The schemas are the [JSON Schemas](super::schema) of the types of the ABI, named by fqn with
+//! `.` instead of `::` in `#/components/schemas`, eg. `project.Person`. Task functions are marked
+//! with `"x-greycat-task": true`.
+
+use serde_json::{json, Map, Value};
+
+use super::schema::{is_root, nullable_reference, primitive_schema, type_schema};
+use super::{Abi, AbiFn};
+
+fn components_path(fqn: &str) -> String {
+    format!("#/components/schemas/{}", component_name(fqn))
+}
+
+/// OpenAPI component names cannot contain `:`
+fn component_name(fqn: &str) -> String {
+    fqn.replace("::", ".")
+}
+
+impl Abi {
+    /// Returns the OpenAPI document of the functions of the ABI, see the [module docs](self)
+    pub fn openapi(&self) -> Value {
+        let paths: Map<String, Value> = self
+            .functions
+            .iter()
+            .map(|function| {
+                (
+                    format!("/{}", function.named_fqn(self)),
+                    json!({ "post": operation(function, self) }),
+                )
+            })
+            .collect();
+
+        let schemas: Map<String, Value> = self
+            .types
+            .iter()
+            .filter(|ty| !is_root(ty, self) && primitive_schema(ty, self).is_none())
+            .map(|ty| {
+                let name = component_name(&ty.named_fqn(self));
+                (name, type_schema(ty, self, components_path))
+            })
+            .collect();
+
+        json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "GreyCat",
+                "version": self.headers.headers.version.to_string(),
+            },
+            "paths": paths,
+            "components": { "schemas": schemas },
+        })
+    }
+}
+
+fn operation(function: &AbiFn, abi: &Abi) -> Value {
+    let fqn = function.named_fqn(abi);
+    let params: Vec<Value> = function
+        .params
+        .iter()
+        .map(|param| {
+            let mut schema =
+                nullable_reference(&param.r#type, param.nullable, abi, components_path);
+            if let Value::Object(schema) = &mut schema {
+                schema.insert("title".to_string(), json!(&abi.symbols[param.name]));
+            }
+            schema
+        })
+        .collect();
+    let nb_params = params.len();
+    let ret = nullable_reference(
+        &function.return_type,
+        function.return_nullable,
+        abi,
+        components_path,
+    );
+
+    let mut operation = json!({
+        "operationId": fqn,
+        "tags": [&abi.symbols[function.module]],
+        "requestBody": {
+            "required": true,
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "array",
+                        "prefixItems": params,
+                        "minItems": nb_params,
+                        "maxItems": nb_params,
+                    },
+                },
+            },
+        },
+        "responses": {
+            "200": {
+                "description": format!("the value returned by `{fqn}`"),
+                "content": {
+                    "application/json": { "schema": ret },
+                },
+            },
+        },
+    });
+    if function.is_task {
+        operation["x-greycat-task"] = json!(true);
+    }
+    operation
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn openapi_paths() {
+        let abi = project_abi();
+        let doc = abi.openapi();
+
+        let add = &doc["paths"]["/project::add"]["post"];
+        assert_eq!(add["operationId"], "project::add");
+        assert_eq!(
+            add["requestBody"]["content"]["application/json"]["schema"],
+            json!({
+                "type": "array",
+                "prefixItems": [
+                    { "type": "integer", "title": "a" },
+                    { "type": "integer", "title": "b" },
+                ],
+                "minItems": 2,
+                "maxItems": 2,
+            })
+        );
+        assert_eq!(
+            add["responses"]["200"]["content"]["application/json"]["schema"],
+            json!({ "type": "integer" })
+        );
+        assert!(add.get("x-greycat-task").is_none());
+        assert_eq!(
+            doc["components"]["schemas"]["project.Person"]["title"],
+            "project::Person"
+        );
+    }
+}
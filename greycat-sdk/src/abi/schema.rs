@@ -0,0 +1,339 @@
+//! [JSON Schema](https://json-schema.org/draft/2020-12) of the types of an [`Abi`].
+//!
+//! The schemas describe values in the [typed JSON](crate::typed_json) exchanged with a GreyCat
+//! server, as written by [`Value::to_typed_json`](crate::value::Value::to_typed_json):
+//!
+//! - objects are JSON objects keyed by attribute name and tagged with their fqn, eg.
+//!   `{ "_type": "project::Person", "name": "Bob" }`, nullable attributes may be missing
+//! - enums are `{ "_type": "project::Status", "field": "Active" }`
+//! - `char`, `time`, `duration`, `geo` and nodes are tagged too, eg.
+//!   `{ "_type": "core::node", "ref": "1A" }`
+//! - `Array` is an array and `Map` is `{ "_type": "core::Map", "values": [key, value, ...] }`,
+//!   the ABI does not tell the type of their elements
+//! - the JSON of the other native types is not known, their schemas accept anything
+//!
+//! Types other than the core primitives are referenced from `$defs` by their fqn, eg.
+//! `{ "$ref": "#/$defs/project::Person" }`.
+//!
+//! An abstract type is `oneOf` its concrete [subtypes](AbiType::is_subtype_of).
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use super::{Abi, AbiAttr, AbiType};
+use crate::typed_json::TYPE;
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// Returns the JSON pointer to the definition of the type `fqn`
+pub(super) type RefPath = fn(&str) -> String;
+
+fn defs_path(fqn: &str) -> String {
+    format!("#/$defs/{fqn}")
+}
+
+impl Abi {
+    /// Returns a JSON Schema document with the schema of every type of the ABI in its `$defs`
+    pub fn json_schema(&self) -> Value {
+        let defs: Map<String, Value> = self
+            .types
+            .iter()
+            .filter(|ty| !is_root(ty, self) && primitive_schema(ty, self).is_none())
+            .map(|ty| (ty.named_fqn(self), type_schema(ty, self, defs_path)))
+            .collect();
+
+        json!({
+            "$schema": DRAFT,
+            "$defs": defs,
+        })
+    }
+}
+
+impl AbiType {
+    /// Returns a JSON Schema document for the values of this type, the types it references are
+    /// in its `$defs`
+    pub fn json_schema(&self, abi: &Abi) -> Value {
+        let mut defs = BTreeMap::new();
+        let mut stack = references(self, abi);
+        while let Some(ty) = stack.pop() {
+            let fqn = ty.named_fqn(abi);
+            if fqn == self.named_fqn(abi) || defs.contains_key(&fqn) {
+                continue;
+            }
+            stack.extend(references(&ty, abi));
+            defs.insert(fqn, type_schema(&ty, abi, defs_path));
+        }
+
+        let mut schema = Map::new();
+        schema.insert("$schema".to_string(), json!(DRAFT));
+        match type_schema(self, abi, defs_path) {
+            Value::Object(fields) => schema.extend(fields),
+            other => {
+                schema.insert("allOf".to_string(), json!([other]));
+            }
+        }
+        if !defs.is_empty() {
+            schema.insert("$defs".to_string(), json!(defs));
+        }
+        Value::Object(schema)
+    }
+}
+
+/// Returns the schema of `ty`, without `$schema` nor `$defs`
+pub(super) fn type_schema(ty: &AbiType, abi: &Abi, ref_path: RefPath) -> Value {
+    if let Some(schema) = primitive_schema(ty, abi) {
+        return schema;
+    }
+
+    let fqn = ty.named_fqn(abi);
+    let attrs = ty.attrs.as_deref().unwrap_or_default();
+
+    if ty.is_enum {
+        let variants: Vec<_> = attrs.iter().map(|attr| &abi.symbols[attr.name]).collect();
+        let mut schema = tagged(
+            &fqn,
+            json!({ "field": { "type": "string", "enum": variants } }),
+            &["field"],
+        );
+        schema["title"] = json!(fqn);
+        return schema;
+    }
+
+    if ty.is_abstract {
+        let concrete: Vec<_> = subtypes(ty, abi)
+            .iter()
+            .map(|ty| reference(ty, abi, ref_path))
+            .collect();
+        return json!({ "title": fqn, "oneOf": concrete });
+    }
+
+    if ty.is_native {
+        return json!({ "title": fqn, "description": "native type" });
+    }
+
+    let properties: Map<String, Value> = attrs
+        .iter()
+        .map(|attr| {
+            (
+                abi.symbols[attr.name].to_string(),
+                attr_schema(attr, abi, ref_path),
+            )
+        })
+        .collect();
+    let required: Vec<_> = attrs
+        .iter()
+        .filter(|attr| !attr.nullable)
+        .map(|attr| &abi.symbols[attr.name])
+        .collect();
+
+    let mut schema = tagged(&fqn, Value::Object(properties), &required);
+    schema["title"] = json!(fqn);
+    schema
+}
+
+/// Returns the schema of an object tagged with `fqn`, with the given `properties`
+fn tagged(fqn: &str, properties: Value, required: &[&str]) -> Value {
+    let mut tagged = Map::new();
+    tagged.insert(TYPE.to_string(), json!({ "const": fqn }));
+    if let Value::Object(properties) = properties {
+        tagged.extend(properties);
+    }
+    let required: Vec<_> = std::iter::once(TYPE)
+        .chain(required.iter().copied())
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": tagged,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+fn attr_schema(attr: &AbiAttr, abi: &Abi, ref_path: RefPath) -> Value {
+    match attr.prog_type(abi) {
+        Some(ty) => nullable_reference(&ty, attr.nullable, abi, ref_path),
+        None => json!({}),
+    }
+}
+
+/// Returns the [`reference`] to `ty`, that also accepts `null` when `nullable`
+pub(super) fn nullable_reference(
+    ty: &AbiType,
+    nullable: bool,
+    abi: &Abi,
+    ref_path: RefPath,
+) -> Value {
+    let schema = reference(ty, abi, ref_path);
+    if nullable && schema != json!({ "type": "null" }) {
+        json!({ "anyOf": [schema, { "type": "null" }] })
+    } else {
+        schema
+    }
+}
+
+/// Returns the schema of the core primitives, which are inlined instead of referenced
+pub(super) fn primitive_schema(ty: &AbiType, abi: &Abi) -> Option<Value> {
+    if &abi.symbols[ty.module] != "core" {
+        return None;
+    }
+
+    let name = &abi.symbols[ty.name];
+    let fqn = format!("core::{name}");
+    let schema = match name {
+        "any" => json!({}),
+        "null" => json!({ "type": "null" }),
+        "bool" => json!({ "type": "boolean" }),
+        "int" => json!({ "type": "integer" }),
+        "float" => json!({ "type": "number" }),
+        "String" => json!({ "type": "string" }),
+        "char" => tagged(
+            &fqn,
+            json!({ "value": { "type": "string", "minLength": 1, "maxLength": 1 } }),
+            &["value"],
+        ),
+        "time" => json!({
+            "oneOf": [
+                tagged(&fqn, json!({ "iso": { "type": "string", "format": "date-time" } }), &["iso"]),
+                tagged(
+                    &fqn,
+                    json!({ "us": {
+                        "type": "integer",
+                        "description": "microseconds since 1970-01-01T00:00:00Z",
+                    } }),
+                    &["us"],
+                ),
+            ],
+        }),
+        "duration" => tagged(
+            &fqn,
+            json!({
+                "s": { "type": "integer", "description": "seconds" },
+                "us": { "type": "integer", "description": "microseconds, added to `s`" },
+            }),
+            &[],
+        ),
+        "geo" => tagged(
+            &fqn,
+            json!({
+                "lat": { "type": "number", "minimum": -90, "maximum": 90 },
+                "lng": { "type": "number", "minimum": -180, "maximum": 180 },
+            }),
+            &["lat", "lng"],
+        ),
+        "node" | "nodeTime" | "nodeIndex" | "nodeList" | "nodeGeo" => tagged(
+            &fqn,
+            json!({ "ref": {
+                "type": "string",
+                "pattern": "^[0-9A-F]+$",
+                "description": "the id of the node, in hexadecimal",
+            } }),
+            &["ref"],
+        ),
+        "Array" => json!({ "type": "array" }),
+        "Map" => tagged(
+            &fqn,
+            json!({ "values": {
+                "type": "array",
+                "description": "the keys followed by their value",
+            } }),
+            &["values"],
+        ),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+/// Returns the inlined schema of a core primitive, a `$ref` to the definition of `ty` otherwise
+fn reference(ty: &AbiType, abi: &Abi, ref_path: RefPath) -> Value {
+    primitive_schema(ty, abi).unwrap_or_else(|| json!({ "$ref": ref_path(&ty.named_fqn(abi)) }))
+}
+
+/// Returns the types referenced by the schema of `ty`
+fn references(ty: &AbiType, abi: &Abi) -> Vec<Arc<AbiType>> {
+    let referenced = if ty.is_abstract {
+        subtypes(ty, abi)
+    } else if ty.is_enum {
+        Vec::new()
+    } else {
+        ty.attrs
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|attr| attr.prog_type(abi))
+            .collect()
+    };
+    referenced
+        .into_iter()
+        .filter(|ty| primitive_schema(ty, abi).is_none())
+        .collect()
+}
+
+/// Returns the concrete [subtypes](AbiType::is_subtype_of) of `ty`
+fn subtypes(ty: &AbiType, abi: &Abi) -> Vec<Arc<AbiType>> {
+    abi.types
+        .iter()
+        .filter(|sub| !sub.is_abstract && !is_root(sub, abi) && sub.is_subtype_of(ty))
+        .cloned()
+        .collect()
+}
+
+/// Returns `true` for the module variables holder
+#[inline]
+pub(super) fn is_root(ty: &AbiType, abi: &Abi) -> bool {
+    ty.module == 0 && &abi.symbols[ty.name] == "$$$root"
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use crate::abi::test::{project_abi, rebuild_abi};
+
+    #[test]
+    fn json_schema_of_types() {
+        let abi = project_abi();
+        // makes `age` nullable and a `Status`
+        let abi = rebuild_abi(&abi, &[], |name, attrs| {
+            if name == "Person" {
+                attrs[1].nullable = true;
+                attrs[1].prog_type_offset = 5;
+            }
+        });
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+
+        assert_eq!(
+            person.json_schema(&abi),
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "project::Person",
+                "type": "object",
+                "properties": {
+                    "_type": { "const": "project::Person" },
+                    "name": { "type": "string" },
+                    "age": { "anyOf": [{ "$ref": "#/$defs/project::Status" }, { "type": "null" }] },
+                },
+                "required": ["_type", "name"],
+                "additionalProperties": false,
+                "$defs": {
+                    "project::Status": {
+                        "title": "project::Status",
+                        "type": "object",
+                        "properties": {
+                            "_type": { "const": "project::Status" },
+                            "field": { "type": "string", "enum": ["Active", "Inactive"] },
+                        },
+                        "required": ["_type", "field"],
+                        "additionalProperties": false,
+                    },
+                },
+            })
+        );
+
+        let bundle = abi.json_schema();
+        let defs: Vec<_> = bundle["$defs"].as_object().unwrap().keys().collect();
+        assert_eq!(defs, ["project::Person", "project::Status"]);
+    }
+}
@@ -0,0 +1,138 @@
+//! Calling the functions of a GreyCat server, as done by the code generated by
+//! [`codegen`](crate::codegen).
+
+use std::sync::OnceLock;
+
+use crate::abi::{Abi, AbiHeaders};
+use crate::error::{Error, Result};
+use crate::serialize::AbiSerialize;
+use crate::value::{HeaderValue, Value};
+
+/// The function of a GreyCat server that returns its ABI, starting with its [`AbiHeaders`]
+const SERVER_ABI: &str = "runtime::Runtime::abi";
+
+/// Sends the encoded body of a request to a GreyCat server and returns the body of its response.
+///
+/// Any `Fn(&str, Vec<u8>) -> Result<Vec<u8>>` is a transport.
+pub trait Transport {
+    /// Calls the function `fqn` (eg. `project::add`) with the given request `body`
+    fn send(&self, fqn: &str, body: Vec<u8>) -> Result<Vec<u8>>;
+}
+
+impl<F> Transport for F
+where
+    F: Fn(&str, Vec<u8>) -> Result<Vec<u8>>,
+{
+    #[inline]
+    fn send(&self, fqn: &str, body: Vec<u8>) -> Result<Vec<u8>> {
+        self(fqn, body)
+    }
+}
+
+/// Calls the functions of an [`Abi`] through a [`Transport`]
+pub struct Client<T> {
+    abi: Abi,
+    transport: T,
+    /// set once the ABI of the server matches `abi`, `None` when not checked
+    checked: Option<OnceLock<()>>,
+}
+
+impl<T: Transport> Client<T> {
+    /// Creates a client that checks, on its first call, that the ABI served by the server has the
+    /// same headers and CRC as `abi`, as payloads only carry the headers and not the CRC
+    pub fn new(abi: Abi, transport: T) -> Self {
+        Self {
+            abi,
+            transport,
+            checked: Some(OnceLock::new()),
+        }
+    }
+
+    /// Same as [`Client::new`] but does not check the ABI of the server against `abi`
+    pub fn new_unchecked(abi: Abi, transport: T) -> Self {
+        Self {
+            abi,
+            transport,
+            checked: None,
+        }
+    }
+
+    #[inline]
+    pub fn abi(&self) -> &Abi {
+        &self.abi
+    }
+
+    /// Calls the function `fqn` with `args`, validated against its parameters, and decodes the
+    /// response.
+    pub fn call(&self, fqn: &str, args: &[Value]) -> Result<Value<'_>> {
+        if let Some(checked) = &self.checked {
+            if checked.get().is_none() {
+                self.check_server_abi()?;
+                let _ = checked.set(());
+            }
+        }
+        let function = self
+            .abi
+            .get_fn_by_fqn(fqn)
+            .ok_or_else(|| Error::UnknownFunction {
+                fqn: fqn.to_string(),
+            })?;
+        let body = function.encode_request(args, &self.abi)?;
+        let response = self.transport.send(fqn, body)?;
+        let HeaderValue { value, .. } = HeaderValue::from_reader(&response[..], &self.abi)?;
+        Ok(value)
+    }
+
+    /// Checks the headers and the CRC of the ABI served by the server against the ABI of this
+    /// client
+    pub fn check_server_abi(&self) -> Result<()> {
+        let mut body = Vec::new();
+        self.abi.headers.headers.write_to(&mut body, &self.abi)?;
+        let response = self.transport.send(SERVER_ABI, body)?;
+        let headers = AbiHeaders::from_bytes(&mut &response[..])?;
+        self.abi.check_abi_headers(&headers)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::abi::AbiMismatch;
+
+    /// A server of `project_abi()` whose ABI has the given `crc`, and that answers `3` to calls
+    fn server(crc: u64) -> impl Fn(&str, Vec<u8>) -> Result<Vec<u8>> {
+        move |fqn, _body| {
+            let abi = project_abi();
+            let mut response = Vec::new();
+            if fqn == SERVER_ABI {
+                AbiHeaders { crc, ..abi.headers }.write_to(&mut response, &abi)?;
+            } else {
+                abi.headers.headers.write_to(&mut response, &abi)?;
+                Value::Int(3).write_to(&mut response, &abi)?;
+            }
+            Ok(response)
+        }
+    }
+
+    #[test]
+    fn calls_check_the_server_abi() {
+        let crc = project_abi().headers.crc;
+        let args = [Value::Int(1), Value::Int(2)];
+
+        let client = Client::new(project_abi(), server(crc));
+        assert_eq!(client.call("project::add", &args).unwrap(), Value::Int(3));
+
+        let stale = Client::new(project_abi(), server(crc + 1));
+        assert!(matches!(
+            stale.call("project::add", &args),
+            Err(Error::AbiMismatch(AbiMismatch::Crc { .. }))
+        ));
+        let unchecked = Client::new_unchecked(project_abi(), server(crc + 1));
+        assert_eq!(
+            unchecked.call("project::add", &args).unwrap(),
+            Value::Int(3)
+        );
+    }
+}
@@ -0,0 +1,663 @@
+//! Generates Rust code from an [`Abi`]: a struct for each type, an enum for each enum and a typed
+//! function for each function of the project.
+//!
+//! The types and functions of the `std` library are not generated, the values of those types are
+//! kept as [`Value`](crate::value::Value)s, as are the values of abstract and native types.
+//!
+//! The generated code is meant to be written by a `build.rs` and included:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("project.rs");
+//!     greycat_sdk::codegen::generate_file("gcdata/abi", out).unwrap();
+//!     println!("cargo:rerun-if-changed=gcdata/abi");
+//! }
+//!
+//! // src/lib.rs
+//! include!(concat!(env!("OUT_DIR"), "/project.rs"));
+//!
+//! let sum = project::add(&client, 1, 2)?;
+//! ```
+//!
+//! Each module of the ABI becomes a `pub mod`, attributes, parameters and functions are named in
+//! snake_case, nullable ones are `Option`s. The generated types implement [`GcType`](crate::gc_type::GcType).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::abi::{Abi, AbiBuilder, AbiFn, AbiType};
+use crate::error::Result;
+
+const SDK: &str = "::greycat_sdk";
+const VALUE: &str = "::greycat_sdk::value::Value<'abi>";
+
+/// Generates the Rust code for `abi`
+pub fn generate(abi: &Abi) -> String {
+    Generator::new(abi).generate()
+}
+
+/// Reads the ABI file at `abi_path` and writes the generated code to `out_path`, for `build.rs`
+pub fn generate_file<P, Q>(abi_path: P, out_path: Q) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let bytes = std::fs::read(abi_path)?;
+    let abi = AbiBuilder::new().build(&*bytes)?;
+    std::fs::write(out_path, generate(&abi))?;
+    Ok(())
+}
+
+struct Generator<'abi> {
+    abi: &'abi Abi,
+    /// the types generated as structs, by offset, with whether they need the `'abi` lifetime
+    structs: BTreeMap<u32, bool>,
+    /// the types generated as enums, by offset
+    enums: BTreeSet<u32>,
+}
+
+/// The functions of a module, methods are grouped by type name
+#[derive(Default)]
+struct ModuleFns<'abi> {
+    functions: Vec<&'abi AbiFn>,
+    methods: BTreeMap<&'abi str, Vec<&'abi AbiFn>>,
+}
+
+impl<'abi> Generator<'abi> {
+    fn new(abi: &'abi Abi) -> Self {
+        let mut structs = BTreeMap::new();
+        let mut enums = BTreeSet::new();
+        for ty in abi.types.iter() {
+            if !is_generated(ty, abi) {
+                continue;
+            }
+            if ty.is_enum {
+                enums.insert(ty.offset);
+            } else if !ty.is_abstract {
+                structs.insert(ty.offset, false);
+            }
+        }
+
+        let mut generator = Self {
+            abi,
+            structs,
+            enums,
+        };
+
+        // a struct needs the lifetime when one of its fields does, until nothing changes
+        loop {
+            let changed: Vec<u32> = generator
+                .structs
+                .iter()
+                .filter(|(offset, lifetime)| {
+                    !**lifetime
+                        && generator
+                            .fields(**offset)
+                            .iter()
+                            .any(|(_, _, ty)| ty.contains("'abi"))
+                })
+                .map(|(offset, _)| *offset)
+                .collect();
+            if changed.is_empty() {
+                break;
+            }
+            for offset in changed {
+                generator.structs.insert(offset, true);
+            }
+        }
+
+        generator
+    }
+
+    fn generate(&self) -> String {
+        let mut types: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        for offset in self.structs.keys().chain(&self.enums) {
+            let ty = &self.abi.types[*offset as usize];
+            types.entry(ty.module).or_default().push(*offset);
+        }
+
+        let mut functions: BTreeMap<u32, ModuleFns> = BTreeMap::new();
+        for function in self.abi.functions.iter() {
+            if &self.abi.symbols[function.lib_name] == "std" {
+                continue;
+            }
+            let entry = functions.entry(function.module).or_default();
+            match function.r#type {
+                Some(ty) => entry
+                    .methods
+                    .entry(&self.abi.symbols[ty])
+                    .or_default()
+                    .push(function),
+                None => entry.functions.push(function),
+            }
+        }
+
+        let mut modules: Vec<u32> = types.keys().chain(functions.keys()).copied().collect();
+        modules.sort_unstable_by_key(|module| &self.abi.symbols[*module]);
+        modules.dedup();
+
+        let mut out = String::from("// Generated by greycat-sdk from the ABI, do not edit.\n");
+        for module in modules {
+            let name = &self.abi.symbols[module];
+            writeln!(out).unwrap();
+            writeln!(
+                out,
+                "#[allow(non_camel_case_types, clippy::too_many_arguments)]"
+            )
+            .unwrap();
+            writeln!(out, "pub mod {} {{", ident(name)).unwrap();
+
+            let mut items = String::new();
+            for offset in types.get(&module).into_iter().flatten() {
+                if self.enums.contains(offset) {
+                    self.write_enum(&mut items, *offset);
+                } else {
+                    self.write_struct(&mut items, *offset);
+                }
+            }
+
+            if let Some(fns) = functions.get(&module) {
+                for function in &fns.functions {
+                    self.write_fn(
+                        &mut items,
+                        function,
+                        &ident(&snake_case(&self.abi.symbols[function.name])),
+                        false,
+                    );
+                }
+                for (ty_name, methods) in &fns.methods {
+                    self.write_methods(&mut items, name, ty_name, methods);
+                }
+            }
+
+            // skips the leading empty line
+            write_indented(&mut out, items.strip_prefix('\n').unwrap_or(&items));
+            writeln!(out, "}}").unwrap();
+        }
+        out
+    }
+
+    fn write_struct(&self, out: &mut String, offset: u32) {
+        let ty = &self.abi.types[offset as usize];
+        let fqn = ty.named_fqn(self.abi);
+        let (name, generics) = self.struct_name(offset);
+        let fields = self.fields(offset);
+
+        writeln!(out).unwrap();
+        writeln!(out, "/// `{fqn}`").unwrap();
+        writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+        writeln!(out, "pub struct {name}{generics} {{").unwrap();
+        for (_, field, ty) in &fields {
+            writeln!(out, "    pub {field}: {ty},").unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "impl<'abi> {SDK}::gc_type::GcType<'abi> for {name}{generics} {{"
+        )
+        .unwrap();
+        writeln!(out, "    const TYPE: &'static str = {fqn:?};").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "    fn to_value(&self, abi: &'abi {SDK}::abi::Abi) -> {SDK}::Result<{VALUE}> {{"
+        )
+        .unwrap();
+        writeln!(out, "        {SDK}::gc_type::object(abi, Self::TYPE, vec![").unwrap();
+        for (attr, field, _) in &fields {
+            writeln!(
+                out,
+                "            ({attr:?}, {SDK}::gc_type::GcType::to_value(&self.{field}, abi)?),"
+            )
+            .unwrap();
+        }
+        writeln!(out, "        ])").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "    fn from_value(value: &{VALUE}, abi: &'abi {SDK}::abi::Abi) -> {SDK}::Result<Self> {{"
+        )
+        .unwrap();
+        if fields.is_empty() {
+            writeln!(
+                out,
+                "        {SDK}::gc_type::as_object(value, Self::TYPE, abi)?;"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                out,
+                "        let obj = {SDK}::gc_type::as_object(value, Self::TYPE, abi)?;"
+            )
+            .unwrap();
+        }
+        writeln!(out, "        Ok(Self {{").unwrap();
+        for (attr, field, _) in &fields {
+            writeln!(
+                out,
+                "            {field}: {SDK}::gc_type::GcType::from_value(obj.get({attr:?}, abi)?, abi)?,"
+            )
+            .unwrap();
+        }
+        writeln!(out, "        }})").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    fn write_enum(&self, out: &mut String, offset: u32) {
+        let ty = &self.abi.types[offset as usize];
+        let fqn = ty.named_fqn(self.abi);
+        let name = ident(&self.abi.symbols[ty.name]);
+        let variants: Vec<(&str, String)> = ty
+            .attrs
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .map(|attr| {
+                let key = &self.abi.symbols[attr.name];
+                (key, ident(key))
+            })
+            .collect();
+
+        writeln!(out).unwrap();
+        writeln!(out, "/// `{fqn}`").unwrap();
+        writeln!(out, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]").unwrap();
+        writeln!(out, "pub enum {name} {{").unwrap();
+        for (_, variant) in &variants {
+            writeln!(out, "    {variant},").unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+
+        writeln!(out).unwrap();
+        writeln!(out, "impl<'abi> {SDK}::gc_type::GcType<'abi> for {name} {{").unwrap();
+        writeln!(out, "    const TYPE: &'static str = {fqn:?};").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "    fn to_value(&self, abi: &'abi {SDK}::abi::Abi) -> {SDK}::Result<{VALUE}> {{"
+        )
+        .unwrap();
+        if variants.is_empty() {
+            writeln!(out, "        let _ = abi;").unwrap();
+            writeln!(out, "        match *self {{}}").unwrap();
+        } else {
+            writeln!(out, "        let key = match self {{").unwrap();
+            for (key, variant) in &variants {
+                writeln!(out, "            Self::{variant} => {key:?},").unwrap();
+            }
+            writeln!(out, "        }};").unwrap();
+            writeln!(
+                out,
+                "        {SDK}::gc_type::enum_value(abi, Self::TYPE, key)"
+            )
+            .unwrap();
+        }
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "    fn from_value(value: &{VALUE}, abi: &'abi {SDK}::abi::Abi) -> {SDK}::Result<Self> {{"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "        match {SDK}::gc_type::enum_key(value, Self::TYPE, abi)? {{"
+        )
+        .unwrap();
+        for (key, variant) in &variants {
+            writeln!(out, "            {key:?} => Ok(Self::{variant}),").unwrap();
+        }
+        writeln!(
+            out,
+            "            key => Err({SDK}::Error::UnknownEnumKey {{ ty: Self::TYPE.to_string(), key: key.to_string() }}),"
+        )
+        .unwrap();
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    /// Writes the `methods` of the type `ty_name` in an `impl` block when the type is generated,
+    /// as module functions prefixed by the type name otherwise
+    fn write_methods(&self, out: &mut String, module: &str, ty_name: &str, methods: &[&AbiFn]) {
+        let ty = self
+            .abi
+            .get_type_by_fqn(&format!("{module}::{ty_name}"))
+            .filter(|ty| self.structs.contains_key(&ty.offset) || self.enums.contains(&ty.offset));
+
+        match ty {
+            Some(ty) => {
+                let (name, generics) = self.struct_name(ty.offset);
+                let lifetime = !generics.is_empty();
+                writeln!(out).unwrap();
+                writeln!(out, "impl{generics} {name}{generics} {{").unwrap();
+                for (i, function) in methods.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(out).unwrap();
+                    }
+                    let name = ident(&snake_case(&self.abi.symbols[function.name]));
+                    let mut method = String::new();
+                    self.write_fn(&mut method, function, &name, lifetime);
+                    write_indented(out, method.strip_prefix('\n').unwrap_or(&method));
+                }
+                writeln!(out, "}}").unwrap();
+            }
+            None => {
+                for function in methods {
+                    let name = format!(
+                        "{}_{}",
+                        snake_case(ty_name),
+                        snake_case(&self.abi.symbols[function.name])
+                    );
+                    self.write_fn(out, function, &ident(&name), false);
+                }
+            }
+        }
+    }
+
+    /// Writes `function`, `in_lifetime` is `true` when `'abi` is already declared by the `impl`
+    fn write_fn(&self, out: &mut String, function: &AbiFn, name: &str, in_lifetime: bool) {
+        let params: Vec<(String, String)> = function
+            .params
+            .iter()
+            .map(|param| {
+                let name = match snake_case(&self.abi.symbols[param.name]).as_str() {
+                    "client" => "client_".to_string(),
+                    name => ident(name),
+                };
+                (name, self.rust_type(&param.r#type, param.nullable, None))
+            })
+            .collect();
+        let ret = self.rust_type(
+            &function.return_type,
+            function.return_nullable && !is_null(&function.return_type, self.abi),
+            None,
+        );
+
+        let uses_lifetime =
+            ret.contains("'abi") || params.iter().any(|(_, ty)| ty.contains("'abi"));
+        let generics = if uses_lifetime && !in_lifetime {
+            format!("<'abi, T: {SDK}::client::Transport>")
+        } else {
+            format!("<T: {SDK}::client::Transport>")
+        };
+        let client = if uses_lifetime { "&'abi " } else { "&" };
+
+        writeln!(out).unwrap();
+        writeln!(out, "/// Calls `{}`", function.named_fqn(self.abi)).unwrap();
+        write!(
+            out,
+            "pub fn {name}{generics}(client: {client}{SDK}::client::Client<T>"
+        )
+        .unwrap();
+        for (name, ty) in &params {
+            write!(out, ", {name}: {ty}").unwrap();
+        }
+        writeln!(out, ") -> {SDK}::Result<{ret}> {{").unwrap();
+        writeln!(out, "    let value = client.call(").unwrap();
+        writeln!(out, "        {:?},", function.named_fqn(self.abi)).unwrap();
+        writeln!(out, "        &[").unwrap();
+        for (name, _) in &params {
+            writeln!(
+                out,
+                "            {SDK}::gc_type::GcType::to_value(&{name}, client.abi())?,"
+            )
+            .unwrap();
+        }
+        writeln!(out, "        ],").unwrap();
+        writeln!(out, "    )?;").unwrap();
+        writeln!(
+            out,
+            "    {SDK}::gc_type::GcType::from_value(&value, client.abi())"
+        )
+        .unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    /// Returns the name of the generated type at `offset` and its generics, if any
+    fn struct_name(&self, offset: u32) -> (String, &'static str) {
+        let ty = &self.abi.types[offset as usize];
+        let generics = match self.structs.get(&offset) {
+            Some(true) => "<'abi>",
+            _ => "",
+        };
+        (ident(&self.abi.symbols[ty.name]), generics)
+    }
+
+    /// Returns the attribute name, field name and field type of each attribute of the struct at `offset`
+    fn fields(&self, offset: u32) -> Vec<(&'abi str, String, String)> {
+        let ty = &self.abi.types[offset as usize];
+        ty.attrs
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .map(|attr| {
+                let name = &self.abi.symbols[attr.name];
+                let ty = match attr.prog_type(self.abi) {
+                    Some(attr_ty) => self.rust_type(&attr_ty, attr.nullable, Some(offset)),
+                    None => VALUE.to_string(),
+                };
+                (name, ident(&snake_case(name)), ty)
+            })
+            .collect()
+    }
+
+    /// Returns the Rust type of a value of type `ty`, boxed when it is a field of the struct
+    /// `owner` and that struct can be reached from `ty`
+    fn rust_type(&self, ty: &AbiType, nullable: bool, owner: Option<u32>) -> String {
+        let rust_ty = match self.core_type(ty) {
+            Some(rust_ty) => rust_ty,
+            None if self.enums.contains(&ty.offset) => self.path(ty),
+            None => match self.structs.get(&ty.offset) {
+                Some(lifetime) => {
+                    let mut path = self.path(ty);
+                    if *lifetime {
+                        path.push_str("<'abi>");
+                    }
+                    if owner.is_some_and(|owner| self.reaches(ty.offset, owner)) {
+                        format!("::std::boxed::Box<{path}>")
+                    } else {
+                        path
+                    }
+                }
+                None => VALUE.to_string(),
+            },
+        };
+
+        if nullable && rust_ty != "()" {
+            format!("::std::option::Option<{rust_ty}>")
+        } else {
+            rust_ty
+        }
+    }
+
+    fn core_type(&self, ty: &AbiType) -> Option<String> {
+        if &self.abi.symbols[ty.module] != "core" {
+            return None;
+        }
+        let rust_ty = match &self.abi.symbols[ty.name] {
+            "null" => "()".to_string(),
+            "int" => "i64".to_string(),
+            "float" => "f64".to_string(),
+            "bool" => "bool".to_string(),
+            "char" => "char".to_string(),
+            "String" => "::std::string::String".to_string(),
+            "time" => format!("{SDK}::std_n::core::Time"),
+            "duration" => format!("{SDK}::std_n::core::Duration"),
+            "geo" => format!("{SDK}::std_n::core::Geo"),
+            "node" => format!("{SDK}::std_n::core::Node"),
+            "nodeTime" => format!("{SDK}::std_n::core::NodeTime"),
+            "nodeIndex" => format!("{SDK}::std_n::core::NodeIndex"),
+            "nodeList" => format!("{SDK}::std_n::core::NodeList"),
+            "nodeGeo" => format!("{SDK}::std_n::core::NodeGeo"),
+            "Array" => format!("::std::vec::Vec<{VALUE}>"),
+            "Map" => format!("::std::collections::BTreeMap<{VALUE}, {VALUE}>"),
+            _ => VALUE.to_string(),
+        };
+        Some(rust_ty)
+    }
+
+    /// Returns the path of a generated type from within any generated module
+    fn path(&self, ty: &AbiType) -> String {
+        format!(
+            "super::{}::{}",
+            ident(&self.abi.symbols[ty.module]),
+            ident(&self.abi.symbols[ty.name])
+        )
+    }
+
+    /// Returns `true` if the struct `to` can be reached from the struct `from` through their fields
+    fn reaches(&self, from: u32, to: u32) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(offset) = stack.pop() {
+            if offset == to {
+                return true;
+            }
+            if !visited.insert(offset) {
+                continue;
+            }
+            let ty = &self.abi.types[offset as usize];
+            for attr in ty.attrs.as_deref().unwrap_or_default() {
+                if self.structs.contains_key(&attr.prog_type_offset) {
+                    stack.push(attr.prog_type_offset);
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Returns `true` if `ty` is a type of the project, and not a native type
+fn is_generated(ty: &AbiType, abi: &Abi) -> bool {
+    &abi.symbols[ty.lib_name] != "std"
+        && &abi.symbols[ty.module] != "core"
+        && !(ty.module == 0 && &abi.symbols[ty.name] == "$$$root")
+        && !ty.is_native
+}
+
+/// Writes each line of `code` to `out`, indented once
+fn write_indented(out: &mut String, code: &str) {
+    for line in code.lines() {
+        if line.is_empty() {
+            writeln!(out).unwrap();
+        } else {
+            writeln!(out, "    {line}").unwrap();
+        }
+    }
+}
+
+fn is_null(ty: &AbiType, abi: &Abi) -> bool {
+    &abi.symbols[ty.module] == "core" && &abi.symbols[ty.name] == "null"
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_uppercase() {
+            if prev_lower {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+            prev_lower = false;
+        } else {
+            out.push(c);
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+    }
+    out
+}
+
+/// Escapes `name` when it is not a valid Rust identifier
+fn ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+        "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+        "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe",
+        "unsized", "use", "virtual", "where", "while", "yield",
+    ];
+
+    match name {
+        // cannot be raw identifiers
+        "self" | "Self" | "super" | "crate" | "_" => format!("{name}_"),
+        name if KEYWORDS.contains(&name) => format!("r#{name}"),
+        name if name.starts_with(|c: char| c.is_ascii_digit()) => format!("_{name}"),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::client::Client;
+    use crate::gc_type::GcType;
+    use crate::serialize::AbiSerialize;
+    use crate::value::Value;
+
+    /// The code generated for `project_abi()`, compiled along with the tests
+    mod generated {
+        include!("../testdata/codegen_project.rs");
+    }
+
+    #[test]
+    fn generate_project() {
+        let abi = project_abi();
+        let code = generate(&abi);
+
+        assert!(!code.contains("pub mod core"));
+        assert!(code.contains("pub mod project {"));
+        assert!(code.contains(
+            "    pub struct Person {\n        pub name: ::std::string::String,\n        pub age: i64,\n    }"
+        ));
+        assert!(code.contains("    pub enum Status {\n        Active,\n        Inactive,\n    }"));
+        assert!(code.contains(
+            "pub fn add<T: ::greycat_sdk::client::Transport>(client: &::greycat_sdk::client::Client<T>, a: i64, b: i64) -> ::greycat_sdk::Result<i64> {"
+        ));
+        assert_eq!(ident("type"), "r#type");
+        assert_eq!(snake_case("createdAt"), "created_at");
+    }
+
+    #[test]
+    fn generated_code_compiles_and_converts() {
+        let abi = project_abi();
+        assert_eq!(
+            generate(&abi),
+            include_str!("../testdata/codegen_project.rs"),
+            "testdata/codegen_project.rs is outdated"
+        );
+
+        let person = generated::project::Person {
+            name: "John".to_string(),
+            age: 42,
+        };
+        let value = person.to_value(&abi).unwrap();
+        assert_eq!(
+            generated::project::Person::from_value(&value, &abi).unwrap(),
+            person
+        );
+        let status = generated::project::Status::Inactive;
+        let value = status.to_value(&abi).unwrap();
+        assert_eq!(
+            generated::project::Status::from_value(&value, &abi).unwrap(),
+            status
+        );
+
+        let client = Client::new_unchecked(project_abi(), |fqn: &str, _body| {
+            assert_eq!(fqn, "project::add");
+            let abi = project_abi();
+            let mut response = Vec::new();
+            abi.headers.headers.write_to(&mut response, &abi)?;
+            Value::Int(3).write_to(&mut response, &abi)?;
+            Ok(response)
+        });
+        assert_eq!(generated::project::add(&client, 1, 2).unwrap(), 3);
+    }
+}
@@ -0,0 +1,394 @@
+//! Deep type checking of a [`Value`] against an [`AbiType`].
+//!
+//! The checks follow what [`GcObject::write_raw_to`](crate::serialize::AbiSerialize::write_raw_to)
+//! expects, so a conforming value can be written without errors.
+
+use crate::abi::{Abi, AbiAttr, AbiType};
+use crate::gc_enum::GcEnum;
+use crate::gc_object::GcObject;
+use crate::primitive;
+use crate::value::Value;
+
+/// Something wrong with a value, at `path` from the checked value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// eg. `members[2].age`, empty for the checked value itself
+    pub path: String,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The value is not of the expected type, `got` is `null` for a non-nullable value
+    TypeMismatch { expected: String, got: String },
+    /// The object does not have as many values as its type has attributes
+    AttrCount {
+        ty: String,
+        expected: usize,
+        got: usize,
+    },
+    /// The enum value is not one of the variants of its type
+    UnknownVariant { ty: String, variant: String },
+    /// The object is an instance of an abstract type
+    AbstractType { ty: String },
+}
+
+impl std::fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViolationKind::TypeMismatch { expected, got } => {
+                write!(f, "expects {expected}, got {got}")
+            }
+            ViolationKind::AttrCount { ty, expected, got } => {
+                write!(f, "expects {expected} attributes for {ty}, got {got}")
+            }
+            ViolationKind::UnknownVariant { ty, variant } => {
+                write!(f, "expects a variant of {ty}, got {variant}")
+            }
+            ViolationKind::AbstractType { ty } => {
+                write!(f, "expects a concrete type, got abstract {ty}")
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            self.kind.fmt(f)
+        } else {
+            write!(f, "`{}` {}", self.path, self.kind)
+        }
+    }
+}
+
+impl Value<'_> {
+    /// Checks that this value is of type `ty`, recursing into objects, arrays and maps.
+    ///
+    /// Returns every violation found, not only the first one.
+    pub fn conforms_to(&self, ty: &AbiType, abi: &Abi) -> Result<(), Vec<Violation>> {
+        let mut violations = Vec::new();
+        check(self, ty, false, abi, "", &mut violations);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+/// Returns the name GreyCat uses for `ty`, the types of `core` are not prefixed by their module
+pub(crate) fn type_name(ty: &AbiType, abi: &Abi) -> String {
+    match &abi.symbols[ty.module] {
+        "core" => abi.symbols[ty.name].to_string(),
+        _ => ty.named_fqn(abi),
+    }
+}
+
+/// Checks that `value` is of type `ty`, pushing the violations found to `violations`
+pub(crate) fn check(
+    value: &Value,
+    ty: &AbiType,
+    nullable: bool,
+    abi: &Abi,
+    path: &str,
+    violations: &mut Vec<Violation>,
+) {
+    if let Value::Null = value {
+        if !nullable {
+            violations.push(type_mismatch(path, type_name(ty, abi), value, abi));
+        }
+        return;
+    }
+
+    if !is_of_type(value, ty, abi) {
+        violations.push(type_mismatch(path, type_name(ty, abi), value, abi));
+        return;
+    }
+
+    check_content(value, abi, path, violations);
+}
+
+/// Checks the content of `value` against its own type
+fn check_content(value: &Value, abi: &Abi, path: &str, violations: &mut Vec<Violation>) {
+    match value {
+        Value::Obj(obj) => check_object(obj, abi, path, violations),
+        Value::Enum(en) => check_enum(en, abi, path, violations),
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                check_content(value, abi, &format!("{path}[{i}]"), violations);
+            }
+        }
+        Value::Map(entries) => {
+            for (key, value) in entries {
+                check_content(
+                    value,
+                    abi,
+                    &format!("{path}[{}]", key.display(abi)),
+                    violations,
+                );
+            }
+        }
+        Value::Set(values) => {
+            for value in values {
+                check_content(
+                    value,
+                    abi,
+                    &format!("{path}[{}]", value.display(abi)),
+                    violations,
+                );
+            }
+        }
+        Value::Tuple(tuple) => {
+            check_content(&tuple.x, abi, &format!("{path}.x"), violations);
+            check_content(&tuple.y, abi, &format!("{path}.y"), violations);
+        }
+        Value::Table(table) => {
+            for (row, values) in table.iter_rows().enumerate() {
+                for (col, value) in values.iter().enumerate() {
+                    check_content(value, abi, &format!("{path}[{row}][{col}]"), violations);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn check_object(obj: &GcObject, abi: &Abi, path: &str, violations: &mut Vec<Violation>) {
+    if obj.ty.is_abstract {
+        violations.push(Violation {
+            path: path.to_string(),
+            kind: ViolationKind::AbstractType {
+                ty: type_name(&obj.ty, abi),
+            },
+        });
+    }
+
+    let attrs = obj.ty.attrs.as_deref().unwrap_or_default();
+    let values = obj.values.as_deref().unwrap_or_default();
+    if attrs.len() != values.len() {
+        violations.push(Violation {
+            path: path.to_string(),
+            kind: ViolationKind::AttrCount {
+                ty: type_name(&obj.ty, abi),
+                expected: attrs.len(),
+                got: values.len(),
+            },
+        });
+        return;
+    }
+
+    for (attr, value) in attrs.iter().zip(values) {
+        let name = &abi.symbols[attr.name];
+        let path = if path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{path}.{name}")
+        };
+
+        match attr.prog_type(abi) {
+            Some(attr_ty) => {
+                let nb_violations = violations.len();
+                check(value, &attr_ty, attr.nullable, abi, &path, violations);
+                if violations.len() == nb_violations {
+                    check_sbi_type(attr, value, abi, &path, violations);
+                }
+            }
+            None => check_sbi_type(attr, value, abi, &path, violations),
+        }
+    }
+}
+
+fn check_enum(en: &GcEnum, abi: &Abi, path: &str, violations: &mut Vec<Violation>) {
+    let is_variant = en
+        .ty
+        .attrs
+        .as_deref()
+        .and_then(|attrs| attrs.get(en.offset as usize))
+        .is_some_and(|attr| &abi.symbols[attr.name] == en.key);
+    if !is_variant {
+        violations.push(Violation {
+            path: path.to_string(),
+            kind: ViolationKind::UnknownVariant {
+                ty: type_name(&en.ty, abi),
+                variant: en.key.to_string(),
+            },
+        });
+    }
+}
+
+/// Checks that `value` can be written using the binary type of `attr`
+fn check_sbi_type(
+    attr: &AbiAttr,
+    value: &Value,
+    abi: &Abi,
+    path: &str,
+    violations: &mut Vec<Violation>,
+) {
+    if attr.nullable && matches!(value, Value::Null) {
+        return;
+    }
+
+    let expected = match (attr.sbi_type, value) {
+        (primitive::UNDEFINED, _) => return,
+        (primitive::BOOL, Value::Bool(_))
+        | (primitive::CHAR, Value::Char(_))
+        | (primitive::INT, Value::Int(_))
+        | (primitive::FLOAT, Value::Float(_))
+        | (primitive::NODE, Value::Node(_))
+        | (primitive::NODE_TIME, Value::NodeTime(_))
+        | (primitive::NODE_INDEX, Value::NodeIndex(_))
+        | (primitive::NODE_LIST, Value::NodeList(_))
+        | (primitive::NODE_GEO, Value::NodeGeo(_))
+        | (primitive::GEO, Value::Geo(_))
+        | (primitive::TIME, Value::Time(_))
+        | (primitive::DURATION, Value::Duration(_))
+        | (primitive::ENUM, Value::Enum(_))
+        | (primitive::FN, Value::Function(_))
+        | (
+            primitive::OBJECT,
+            Value::Obj(_)
+            | Value::Array(_)
+            | Value::Map(_)
+            | Value::Table(_)
+            | Value::Tensor(_)
+            | Value::Buffer(_)
+            | Value::Tuple(_)
+            | Value::Error(_)
+            | Value::Set(_)
+            | Value::String(_)
+            | Value::Symbol(_)
+            | Value::Enum(_),
+        ) => return,
+        (primitive::BOOL, _) => "bool",
+        (primitive::CHAR, _) => "char",
+        (primitive::INT, _) => "int",
+        (primitive::FLOAT, _) => "float",
+        (primitive::NODE, _) => "node",
+        (primitive::NODE_TIME, _) => "nodeTime",
+        (primitive::NODE_INDEX, _) => "nodeIndex",
+        (primitive::NODE_LIST, _) => "nodeList",
+        (primitive::NODE_GEO, _) => "nodeGeo",
+        (primitive::GEO, _) => "geo",
+        (primitive::TIME, _) => "time",
+        (primitive::DURATION, _) => "duration",
+        (primitive::ENUM, _) => "enum",
+        (primitive::FN, _) => "function",
+        (primitive::OBJECT, _) => "object",
+        _ => return,
+    };
+    violations.push(type_mismatch(path, expected.to_string(), value, abi));
+}
+
+fn type_mismatch(path: &str, expected: String, value: &Value, abi: &Abi) -> Violation {
+    Violation {
+        path: path.to_string(),
+        kind: ViolationKind::TypeMismatch {
+            expected,
+            got: value.type_name(abi),
+        },
+    }
+}
+
+/// Returns `true` if the non-null `value` is of type `ty`, without looking into its content
+fn is_of_type(value: &Value, ty: &AbiType, abi: &Abi) -> bool {
+    if &abi.symbols[ty.module] == "core" {
+        let matches = matches!(
+            (&abi.symbols[ty.name], value),
+            ("any", _)
+                | ("int", Value::Int(_))
+                | ("float", Value::Float(_))
+                | ("bool", Value::Bool(_))
+                | ("char", Value::Char(_))
+                | ("String", Value::String(_) | Value::Symbol(_))
+                | ("Array", Value::Array(_))
+                | ("Map", Value::Map(_))
+                | ("Table", Value::Table(_))
+                | ("Tensor", Value::Tensor(_))
+                | ("Buffer", Value::Buffer(_))
+                | ("Tuple", Value::Tuple(_))
+                | ("Error", Value::Error(_))
+                | ("Set", Value::Set(_))
+                | ("function", Value::Function(_))
+                | ("node", Value::Node(_))
+                | ("nodeTime", Value::NodeTime(_))
+                | ("nodeIndex", Value::NodeIndex(_))
+                | ("nodeList", Value::NodeList(_))
+                | ("nodeGeo", Value::NodeGeo(_))
+                | ("geo", Value::Geo(_))
+                | ("time", Value::Time(_))
+                | ("duration", Value::Duration(_))
+        );
+        if matches {
+            return true;
+        }
+    }
+
+    match value {
+        Value::Enum(en) => ty.is_enum && same_type(&en.ty, ty),
+        Value::Obj(obj) => obj.ty.is_subtype_of(ty),
+        _ => false,
+    }
+}
+
+#[inline]
+fn same_type(a: &AbiType, b: &AbiType) -> bool {
+    a.mapped_abi_type_offset == b.mapped_abi_type_offset
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn conforms_to_reports_every_violation() {
+        let abi = project_abi();
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+        let array = abi.get_type_by_fqn("core::Array").unwrap();
+
+        let bob = Value::Obj(GcObject::new(
+            Arc::clone(&person),
+            Some([Value::String("Bob".into()), Value::Int(42)]),
+        ));
+        assert_eq!(bob.conforms_to(&person, &abi), Ok(()));
+
+        let members = Value::Array(vec![
+            bob,
+            Value::Obj(GcObject::new(
+                Arc::clone(&person),
+                Some([Value::Null, Value::String("42".into())]),
+            )),
+            Value::Obj(GcObject::new(Arc::clone(&person), Some([Value::Int(1)]))),
+        ]);
+        let violations: Vec<_> = members
+            .conforms_to(&array, &abi)
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            violations,
+            [
+                "`[1].name` expects String, got null",
+                "`[1].age` expects int, got String",
+                "`[2]` expects 2 attributes for project::Person, got 1",
+            ]
+        );
+
+        // the keys of maps are displayed in the paths
+        let map = abi.get_type_by_fqn("core::Map").unwrap();
+        let members = Value::Map(BTreeMap::from([(
+            Value::Int(7),
+            Value::Obj(GcObject::new(Arc::clone(&person), Some([Value::Int(1)]))),
+        )]));
+        let violations = members.conforms_to(&map, &abi).unwrap_err();
+        assert_eq!(
+            violations[0].to_string(),
+            "`[7]` expects 2 attributes for project::Person, got 1"
+        );
+    }
+}
@@ -1,12 +1,13 @@
-use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::io::Read;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use anyhow::{anyhow, bail, Result};
 use byteorder::LE;
 
-use crate::abi::{Abi, AbiSymbol, AbiType};
+use crate::abi::{Abi, AbiAttr, AbiSymbol, AbiType};
+use crate::error::{Error, Result};
 use crate::gc_enum::GcEnum;
+use crate::gc_fn::GcFn;
 use crate::gc_object::{attr_is_null, GcObject};
 use crate::primitive;
 use crate::std_n::core::{self, GcString};
@@ -52,13 +53,15 @@ pub trait AbiDeserialize<'abi> {
     ///  - use the type id loader to read the object value
     fn read_object(&mut self, abi: &'abi Abi) -> Result<Value<'abi>>;
     /// Reads an object value using the given `ty` loader
-    fn read_typed_object(&mut self, ty: Rc<AbiType>, abi: &'abi Abi) -> Result<Value<'abi>>;
+    fn read_typed_object(&mut self, ty: Arc<AbiType>, abi: &'abi Abi) -> Result<Value<'abi>>;
     /// Reads a GreyCat enum
     ///  - reads a `vu32` as type id
     ///  - reads a `vu32` as enum field offset
     fn read_enum(&mut self, abi: &'abi Abi) -> Result<GcEnum<'abi>>;
     /// Reads a `vu32` as enum field offset and uses the given `en` id for the enum id
-    fn read_typed_enum(&mut self, en: Rc<AbiType>, abi: &'abi Abi) -> Result<GcEnum<'abi>>;
+    fn read_typed_enum(&mut self, en: Arc<AbiType>, abi: &'abi Abi) -> Result<GcEnum<'abi>>;
+    /// Reads a `vu32` as the offset of a function in the ABI
+    fn read_fn(&mut self, abi: &'abi Abi) -> Result<GcFn<'abi>>;
     /// Reads a value by first reading a `u8` to get the value header type, then calls `read_value_header()` with it
     fn read_value(&mut self, abi: &'abi Abi) -> Result<Value<'abi>>;
     /// Reads a value using the given `header` byte to choose the right type loader
@@ -86,8 +89,10 @@ where
 
     fn read_char(&mut self) -> Result<char> {
         let charcode = byteorder::ReadBytesExt::read_u32::<LE>(self)?;
-        let value = char::from_u32(charcode)
-            .ok_or_else(|| anyhow!("invalid value {charcode} for a char"))?;
+        let value = char::from_u32(charcode).ok_or(Error::InvalidChar {
+            code: charcode,
+            offset: None,
+        })?;
         Ok(value)
     }
 
@@ -134,55 +139,63 @@ where
     fn read_symbol(&mut self, abi: &'abi Abi) -> Result<AbiSymbol<'abi>> {
         let mut symb_id = self.read_vu32()?;
         symb_id >>= 1;
-        Ok(AbiSymbol(&abi.symbols[symb_id]))
+        Ok(AbiSymbol(symbol(abi, symb_id)?))
     }
 
     fn read_string(&mut self, abi: &'abi Abi) -> Result<GcString<'abi>> {
         let len = self.read_vu32()?;
         let value = if len & 1 == 1 {
-            GcString::Symbol(AbiSymbol(&abi.symbols[len >> 1]))
+            GcString::Symbol(AbiSymbol(symbol(abi, len >> 1)?))
         } else {
-            let len = len >> 1;
-            let mut buf = self.take(len as u64);
-            let mut value = String::with_capacity(len as usize);
-            buf.read_to_string(&mut value)?;
-            GcString::String(value)
+            GcString::String(read_utf8(self, len >> 1)?)
         };
         Ok(value)
     }
 
     fn read_object_string(&mut self) -> Result<String> {
         let len = self.read_vu32()?;
-        let mut buf = self.take(len as u64);
-        let mut value = String::with_capacity(len as usize);
-        buf.read_to_string(&mut value)?;
-        Ok(value)
+        read_utf8(self, len)
     }
 
     fn read_object(&mut self, abi: &'abi Abi) -> Result<Value<'abi>> {
         let type_id = self.read_vu32()?;
-        let ty = abi
-            .types
-            .get(type_id)
-            .ok_or_else(|| anyhow!("unknown type with id '{type_id}'"))?;
-        self.read_typed_object(ty, abi)
+        self.read_typed_object(ty(abi, type_id)?, abi)
     }
 
-    fn read_typed_object(&mut self, ty: Rc<AbiType>, abi: &'abi Abi) -> Result<Value<'abi>> {
+    fn read_typed_object(&mut self, ty: Arc<AbiType>, abi: &'abi Abi) -> Result<Value<'abi>> {
         if ty.is_native {
-            // TODO remove this and only call:
-            // (pseudo-code)
-            // let loader = abi.library.get(ty.lib).loaders.get(ty.mapped_abi_type_offset)
-            // return loader(ty, abi)
+            // codecs registered by libraries come first, so that they can override the ones of `core`
+            if let Some(loader) = abi.get_loader(&ty) {
+                return loader.load(self, &ty, abi);
+            }
             match ty.mapped_abi_type_offset {
                 id if id == abi.types.core.string => return Ok(self.read_string(abi)?.into()),
-                _ => todo!("deserializer for \"{}\"", ty.named_fqn(abi)),
+                id if id == abi.types.core.array => {
+                    let len = self.read_vu32()?;
+                    let values = read_vec(len.into(), || self.read_value(abi))?;
+                    return Ok(Value::Array(values));
+                }
+                id if id == abi.types.core.map => {
+                    let len = self.read_vu32()?;
+                    let mut entries = BTreeMap::new();
+                    for _ in 0..len {
+                        let key = self.read_value(abi)?;
+                        entries.insert(key, self.read_value(abi)?);
+                    }
+                    return Ok(Value::Map(entries));
+                }
+                // the layouts of the other core native types are not known: failing is better
+                // than reading wrong values and losing track of the rest of the payload
+                _ => {
+                    return Err(Error::Unsupported {
+                        ty: ty.named_fqn(abi),
+                        offset: None,
+                    })
+                }
             }
         }
 
-        // TODO maybe add a anyhow Context or a better error handling here, even though, this should be
-        // safe to unwrap. Do more evol tests.
-        let prog_type = abi.types.get(ty.mapped_abi_type_offset).unwrap();
+        let prog_type = self::ty(abi, ty.mapped_abi_type_offset)?;
         if let Some(attrs) = ty.attrs.as_ref() {
             let target_attrs_len = prog_type
                 .attrs
@@ -211,33 +224,29 @@ where
                         Value::Enum(self.read_enum(abi)?)
                     }
                     primitive::ENUM => {
-                        let ty = &abi.types[attr.abi_type];
-                        let prog_ty = &abi.types[ty.mapped_abi_type_offset];
+                        let ty = self::ty(abi, attr.abi_type)?;
+                        let prog_ty = self::ty(abi, ty.mapped_abi_type_offset)?;
                         let offset = self.read_vu32()?;
-                        match ty.attrs.as_ref() {
-                            Some(attrs) => {
-                                let attr = &attrs[offset as usize];
-                                let en = GcEnum {
-                                    ty: Rc::clone(prog_ty),
-                                    key: &abi.symbols[attr.name],
-                                    offset: attr.mapped_att_offset,
-                                };
-                                Value::Enum(en)
-                            }
-                            None => bail!("trying to read enum field on a type with no attrs"),
-                        }
+                        let attr = variant(&ty, offset, abi)?;
+                        Value::Enum(GcEnum {
+                            module: symbol(abi, prog_ty.module)?,
+                            name: symbol(abi, prog_ty.name)?,
+                            ty: prog_ty,
+                            key: symbol(abi, attr.name)?,
+                            offset: attr.mapped_att_offset,
+                        })
                     }
                     primitive::OBJECT if attr.sbi_type == primitive::UNDEFINED => {
                         self.read_value(abi)?
                     }
                     primitive::OBJECT => {
-                        let mut attr_obj_ty = &abi.types[attr.abi_type];
+                        let mut attr_obj_ty = self::ty(abi, attr.abi_type)?;
                         if attr_obj_ty.is_abstract {
                             // if the attr type is abstract, we need to determine the concrete type
                             let attr_type_id = self.read_vu32()?;
-                            attr_obj_ty = &abi.types[attr_type_id];
+                            attr_obj_ty = self::ty(abi, attr_type_id)?;
                         }
-                        self.read_typed_object(Rc::clone(attr_obj_ty), abi)?
+                        self.read_typed_object(attr_obj_ty, abi)?
                     }
                     n => self.read_value_header(n, abi)?,
                 };
@@ -248,7 +257,7 @@ where
 
             return Ok(Value::Obj(GcObject {
                 ty: prog_type,
-                values: Some(RefCell::new(values.into_boxed_slice())),
+                values: Some(values.into_boxed_slice()),
             }));
         }
 
@@ -260,28 +269,28 @@ where
 
     fn read_enum(&mut self, abi: &'abi Abi) -> Result<GcEnum<'abi>> {
         let enum_id = self.read_vu32()?;
-        let en = abi
-            .types
-            .get(enum_id)
-            .ok_or_else(|| anyhow!("unknown enum id {enum_id}"))?;
-        self.read_typed_enum(en, abi)
+        self.read_typed_enum(ty(abi, enum_id)?, abi)
     }
 
-    fn read_typed_enum(&mut self, en: Rc<AbiType>, abi: &'abi Abi) -> Result<GcEnum<'abi>> {
+    fn read_typed_enum(&mut self, en: Arc<AbiType>, abi: &'abi Abi) -> Result<GcEnum<'abi>> {
         let offset = self.read_vu32()?;
-        let attrs = en
-            .attrs
-            .as_ref()
-            .ok_or_else(|| anyhow!("enum '{}' has no attributes", &abi.symbols[en.name]))?;
-        let key = attrs[offset as usize].name;
-        let offset = attrs[offset as usize].mapped_att_offset;
+        let attr = variant(&en, offset, abi)?;
+        let key = symbol(abi, attr.name)?;
+        let offset = attr.mapped_att_offset;
         Ok(GcEnum {
+            module: symbol(abi, en.module)?,
+            name: symbol(abi, en.name)?,
             ty: en,
-            key: &abi.symbols[key],
+            key,
             offset,
         })
     }
 
+    fn read_fn(&mut self, abi: &'abi Abi) -> Result<GcFn<'abi>> {
+        let id = self.read_vu32()?;
+        GcFn::new(abi, id).ok_or(Error::UnknownFunctionId { id, offset: None })
+    }
+
     fn read_value(&mut self, abi: &'abi Abi) -> Result<Value<'abi>> {
         let header = byteorder::ReadBytesExt::read_u8(self)?;
         self.read_value_header(header, abi)
@@ -289,6 +298,7 @@ where
 
     fn read_value_header(&mut self, header: u8, abi: &'abi Abi) -> Result<Value<'abi>> {
         let value = match header {
+            primitive::NULL => Value::Null,
             primitive::INT => Value::Int(self.read_int()?),
             primitive::FLOAT => Value::Float(self.read_float()?.into()),
             primitive::BOOL => Value::Bool(self.read_bool()?),
@@ -301,17 +311,74 @@ where
             primitive::GEO => Value::Geo(self.read_geo()?),
             primitive::TIME => Value::Time(self.read_time()?),
             primitive::DURATION => Value::Duration(self.read_duration()?),
-            primitive::FN => todo!("fn ptr are not implemented yet"),
+            primitive::FN => Value::Function(self.read_fn(abi)?),
             primitive::STR_LIT => Value::Symbol(self.read_symbol(abi)?),
             primitive::ENUM => Value::Enum(self.read_enum(abi)?),
             primitive::OBJECT => self.read_object(abi)?,
-            n => anyhow::bail!("unknown primitive type {n}"),
+            n => {
+                return Err(Error::UnknownPrimitive {
+                    header: n,
+                    offset: None,
+                })
+            }
         };
 
         Ok(value)
     }
 }
 
+#[inline]
+pub(crate) fn ty(abi: &Abi, id: u32) -> Result<Arc<AbiType>> {
+    abi.types
+        .get(id)
+        .ok_or(Error::UnknownType { id, offset: None })
+}
+
+#[inline]
+fn symbol(abi: &Abi, id: u32) -> Result<&str> {
+    abi.symbols
+        .resolve(id)
+        .ok_or(Error::UnknownSymbolId { id, offset: None })
+}
+
+/// Returns the attribute of the enum `en` at `offset`
+fn variant<'a>(en: &'a AbiType, offset: u32, abi: &Abi) -> Result<&'a AbiAttr> {
+    en.attrs
+        .as_deref()
+        .and_then(|attrs| attrs.get(offset as usize))
+        .ok_or_else(|| Error::UnknownEnumVariant {
+            ty: en.named_fqn(abi),
+            variant: offset,
+            offset: None,
+        })
+}
+
+/// Reads `len` bytes as an UTF-8 string
+fn read_utf8<R: Read + ?Sized>(reader: &mut R, len: u32) -> Result<String> {
+    let buf = read_bytes(reader, len.into())?;
+    String::from_utf8(buf).map_err(|_| Error::InvalidUtf8 { offset: None })
+}
+
+/// Reads `len` bytes, the buffer grows as the bytes are read as `len` comes from the payload
+fn read_bytes<R: Read + ?Sized>(reader: &mut R, len: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < len {
+        return Err(Error::UnexpectedEof { offset: None });
+    }
+    Ok(buf)
+}
+
+/// Reads `len` items using `read`, the `Vec` grows as the items are read as `len` comes from
+/// the payload
+fn read_vec<T>(len: u64, mut read: impl FnMut() -> Result<T>) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    for _ in 0..len {
+        items.push(read()?);
+    }
+    Ok(items)
+}
+
 // #[test]
 // fn test() {
 //     use crate::serialize::AbiSerialize;
@@ -328,3 +395,53 @@ where
 //     let value = bytes.read_value(&abi).unwrap();
 //     assert_eq!(value, Value::Symbol(AbiSymbol("hello")));
 // }
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::abi::test::native_abi;
+    use crate::serialize::AbiSerialize;
+    use crate::varint::VarintWrite;
+
+    #[test]
+    fn native_types_are_unsupported() {
+        let abi = native_abi();
+        let unsupported = [
+            Value::Table(Box::default()),
+            Value::Tensor(Box::new(
+                core::Tensor::new(core::TensorType::I32, vec![1]).unwrap(),
+            )),
+            Value::Buffer(vec![0, 1, 0xff]),
+            Value::Tuple(Box::default()),
+            Value::Error(Box::new(core::GcError::new("oops"))),
+            Value::Set(BTreeSet::from([Value::Int(1)])),
+        ];
+        for value in unsupported {
+            assert!(matches!(
+                value.write_to(&mut Vec::new(), &abi),
+                Err(Error::Unsupported { .. })
+            ));
+        }
+
+        // `core::Table` to `core::Set` are the types 6 to 11
+        for id in 6..12 {
+            let mut bytes = vec![primitive::OBJECT];
+            bytes.write_vu32(id).unwrap();
+            bytes.write_vu32(0).unwrap();
+            assert!(matches!(
+                (&*bytes).read_value(&abi),
+                Err(Error::Unsupported { .. })
+            ));
+        }
+
+        // lengths come from the payload, they must not be trusted for allocations
+        let mut huge = vec![primitive::OBJECT, 2];
+        huge.write_vu32(u32::MAX).unwrap();
+        assert!(matches!(
+            (&*huge).read_value(&abi),
+            Err(Error::UnexpectedEof { .. })
+        ));
+    }
+}
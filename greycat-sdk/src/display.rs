@@ -0,0 +1,256 @@
+//! Readable representations of a [`Value`], with the names of its types, attributes and enum
+//! variants resolved using the [`Abi`].
+//!
+//! Both adapters are compact with `{}` and pretty, on multiple lines, with `{:#}`.
+
+use std::fmt;
+
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Serialize, Serializer};
+
+use crate::abi::Abi;
+use crate::conform::type_name;
+use crate::value::Value;
+
+/// Displays a value the way GreyCat would, eg. `project::Person { name: "Bob", age: 42 }`
+pub struct DisplayValue<'a, 'abi> {
+    value: &'a Value<'abi>,
+    abi: &'a Abi,
+}
+
+/// Serializes a value to plain JSON, eg. `{"name":"Bob","age":42}`.
+///
+/// Objects are keyed by attribute name and enums are the name of their variant, as described
+/// by their [JSON Schema](crate::abi::schema).
+pub struct JsonValue<'a, 'abi> {
+    value: &'a Value<'abi>,
+    abi: &'a Abi,
+}
+
+impl<'abi> Value<'abi> {
+    #[inline]
+    pub fn display<'a>(&'a self, abi: &'a Abi) -> DisplayValue<'a, 'abi> {
+        DisplayValue { value: self, abi }
+    }
+
+    #[inline]
+    pub fn to_json<'a>(&'a self, abi: &'a Abi) -> JsonValue<'a, 'abi> {
+        JsonValue { value: self, abi }
+    }
+}
+
+impl fmt::Display for DisplayValue<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the `Debug` builders of the formatter already handle the pretty mode
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for DisplayValue<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let abi = self.abi;
+        match self.value {
+            Value::Array(values) => f
+                .debug_list()
+                .entries(values.iter().map(|value| value.display(abi)))
+                .finish(),
+            Value::Map(entries) => f
+                .debug_map()
+                .entries(
+                    entries
+                        .iter()
+                        .map(|(key, value)| (key.display(abi), value.display(abi))),
+                )
+                .finish(),
+            Value::Enum(en) => write!(f, "{}::{}", type_name(&en.ty, abi), en.key),
+            Value::Obj(obj) => {
+                let mut s = f.debug_struct(&type_name(&obj.ty, abi));
+                let attrs = obj.ty.attrs.as_deref().unwrap_or_default();
+                let values = obj.values.as_deref().unwrap_or_default();
+                for (attr, value) in attrs.iter().zip(values) {
+                    s.field(&abi.symbols[attr.name], &value.display(abi));
+                }
+                s.finish()
+            }
+            Value::Set(values) => f
+                .debug_set()
+                .entries(values.iter().map(|value| value.display(abi)))
+                .finish(),
+            Value::Tuple(tuple) => f
+                .debug_tuple("Tuple")
+                .field(&tuple.x.display(abi))
+                .field(&tuple.y.display(abi))
+                .finish(),
+            Value::Table(table) => f
+                .debug_list()
+                .entries(table.iter_rows().map(|row| Row { row, abi }))
+                .finish(),
+            Value::Error(err) => fmt::Display::fmt(err, f),
+            Value::Function(v) => write!(f, "fn {v}"),
+            value => fmt::Debug::fmt(value, f),
+        }
+    }
+}
+
+impl fmt::Display for JsonValue<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = if f.alternate() {
+            serde_json::to_string_pretty(self)
+        } else {
+            serde_json::to_string(self)
+        };
+        f.write_str(&json.map_err(|_| fmt::Error)?)
+    }
+}
+
+impl Serialize for JsonValue<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let abi = self.abi;
+        match self.value {
+            Value::Null => serializer.serialize_unit(),
+            Value::Int(v) => serializer.serialize_i64(*v),
+            Value::Float(v) => serializer.serialize_f64(**v),
+            Value::Char(v) => serializer.serialize_char(*v),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(&value.to_json(abi))?;
+                }
+                seq.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    // JSON keys are strings
+                    match key {
+                        Value::String(key) => map.serialize_key(key)?,
+                        Value::Symbol(key) => map.serialize_key(key.0)?,
+                        key => map.serialize_key(&key.display(abi).to_string())?,
+                    }
+                    map.serialize_value(&value.to_json(abi))?;
+                }
+                map.end()
+            }
+            Value::Symbol(v) => serializer.serialize_str(v.0),
+            Value::Node(v) => serializer.serialize_u64(v.0),
+            Value::NodeTime(v) => serializer.serialize_u64(v.0),
+            Value::NodeIndex(v) => serializer.serialize_u64(v.0),
+            Value::NodeList(v) => serializer.serialize_u64(v.0),
+            Value::NodeGeo(v) => serializer.serialize_u64(v.0),
+            Value::Geo(v) => v.serialize(serializer),
+            Value::Time(v) => serializer.serialize_i64(v.0),
+            Value::Duration(v) => serializer.serialize_i64(v.0),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Enum(en) => serializer.serialize_str(en.key),
+            Value::Obj(obj) => {
+                let attrs = obj.ty.attrs.as_deref().unwrap_or_default();
+                let values = obj.values.as_deref().unwrap_or_default();
+                let mut map = serializer.serialize_map(Some(values.len()))?;
+                for (attr, value) in attrs.iter().zip(values) {
+                    map.serialize_entry(&abi.symbols[attr.name], &value.to_json(abi))?;
+                }
+                map.end()
+            }
+            Value::Table(table) => {
+                let mut seq = serializer.serialize_seq(Some(table.rows()))?;
+                for row in table.iter_rows() {
+                    seq.serialize_element(&Row { row, abi })?;
+                }
+                seq.end()
+            }
+            Value::Tensor(tensor) => {
+                let mut map = serializer.serialize_map(Some(3))?;
+                map.serialize_entry("type", &tensor.ty)?;
+                map.serialize_entry("shape", &tensor.shape)?;
+                map.serialize_entry("values", &tensor.iter().collect::<Vec<_>>())?;
+                map.end()
+            }
+            // binary data is not representable in JSON, this is a best effort
+            Value::Buffer(v) => serializer.serialize_str(&String::from_utf8_lossy(v)),
+            Value::Tuple(tuple) => {
+                let mut seq = serializer.serialize_seq(Some(2))?;
+                seq.serialize_element(&tuple.x.to_json(abi))?;
+                seq.serialize_element(&tuple.y.to_json(abi))?;
+                seq.end()
+            }
+            Value::Error(err) => err.serialize(serializer),
+            Value::Set(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(&value.to_json(abi))?;
+                }
+                seq.end()
+            }
+            Value::Function(v) => serializer.collect_str(v),
+        }
+    }
+}
+
+/// A row of a `core::Table`, displayed and serialized as a list
+struct Row<'a, 'abi> {
+    row: &'a [Value<'abi>],
+    abi: &'a Abi,
+}
+
+impl fmt::Debug for Row<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.row.iter().map(|value| value.display(self.abi)))
+            .finish()
+    }
+}
+
+impl Serialize for Row<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.row.len()))?;
+        for value in self.row {
+            seq.serialize_element(&value.to_json(self.abi))?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::abi::test::project_abi;
+    use crate::gc_object::GcObject;
+    use crate::value::Value;
+
+    #[test]
+    fn display_and_json_with_names() {
+        let abi = project_abi();
+        let bob = GcObject::builder(&abi, "project::Person")
+            .set("name", Value::String("Bob".into()))
+            .set("age", Value::Int(42))
+            .build()
+            .unwrap();
+        let value = Value::Array(vec![
+            Value::Obj(bob),
+            Value::Enum(abi.enum_value("project::Status", "Active").unwrap()),
+        ]);
+
+        assert_eq!(
+            value.display(&abi).to_string(),
+            r#"[project::Person { name: "Bob", age: 42 }, project::Status::Active]"#
+        );
+        assert_eq!(
+            format!("{:#}", value.display(&abi)),
+            "[\n    project::Person {\n        name: \"Bob\",\n        age: 42,\n    },\n    project::Status::Active,\n]"
+        );
+        assert_eq!(
+            value.to_json(&abi).to_string(),
+            r#"[{"name":"Bob","age":42},"Active"]"#
+        );
+        assert_eq!(
+            format!("{:#}", value.to_json(&abi)),
+            "[\n  {\n    \"name\": \"Bob\",\n    \"age\": 42\n  },\n  \"Active\"\n]"
+        );
+    }
+}
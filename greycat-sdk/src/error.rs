@@ -0,0 +1,309 @@
+use std::io::Read;
+
+use crate::abi::AbiMismatch;
+use crate::conform::Violation;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The errors of the SDK.
+///
+/// Decoding errors carry the `offset` of the stream at which they were detected, when known.
+/// It is known when decoding through the entry points of the SDK (eg. [`Abi::new`](crate::abi::Abi::new),
+/// [`HeaderValue::from_reader`](crate::value::HeaderValue::from_reader)), not when calling the
+/// [`AbiDeserialize`](crate::deserialize::AbiDeserialize) methods directly.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The underlying reader or writer failed
+    Io(std::io::Error),
+    /// The stream ended before the value was completely read
+    UnexpectedEof { offset: Option<u64> },
+    /// A string is not valid UTF-8
+    InvalidUtf8 { offset: Option<u64> },
+    /// A char code is not a valid char, or a char cannot be written
+    InvalidChar { code: u32, offset: Option<u64> },
+    /// A value header is not a known primitive type
+    UnknownPrimitive { header: u8, offset: Option<u64> },
+    /// A type id is not defined by the ABI
+    UnknownType { id: u32, offset: Option<u64> },
+    /// A symbol id is not defined by the ABI
+    UnknownSymbolId { id: u32, offset: Option<u64> },
+    /// A function offset is not defined by the ABI
+    UnknownFunctionId { id: u32, offset: Option<u64> },
+    /// A symbol is not defined by the ABI and therefore cannot be written as a symbol
+    UnknownSymbol { symbol: String },
+    /// An enum has no variant at the given offset
+    UnknownEnumVariant {
+        ty: String,
+        variant: u32,
+        offset: Option<u64>,
+    },
+    /// A type has no decoder nor encoder (yet)
+    Unsupported { ty: String, offset: Option<u64> },
+    /// The value of an attribute does not match the type declared by the ABI
+    AttrTypeMismatch {
+        ty: String,
+        attr: String,
+        expected: &'static str,
+        got: String,
+    },
+    /// An object does not have as many values as its type has attributes
+    AttrCountMismatch {
+        ty: String,
+        expected: usize,
+        got: usize,
+    },
+    /// The headers of a payload, or of another ABI, do not match the ABI
+    AbiMismatch(AbiMismatch),
+    /// The ABI itself is malformed
+    InvalidAbi { reason: String },
+    /// A function is called with the wrong number of arguments
+    ArgCount {
+        function: String,
+        expected: usize,
+        got: usize,
+    },
+    /// Some arguments of a function call do not match the function parameters
+    InvalidArgs(Vec<ArgError>),
+    /// A value cannot be converted from a writer ABI to a reader ABI
+    NotConvertible { value: String },
+    /// A type is not defined by the ABI
+    UnknownTypeName { fqn: String },
+    /// A function is not defined by the ABI
+    UnknownFunction { fqn: String },
+    /// A type has no attribute with that name
+    UnknownAttr { ty: String, attr: String },
+    /// Some non-nullable attributes of an object are not set
+    MissingAttrs { ty: String, attrs: Vec<String> },
+    /// An enum has no variant with that key
+    UnknownEnumKey { ty: String, key: String },
+    /// A type is used as an enum but is not one
+    NotAnEnum { ty: String },
+    /// A value cannot be converted to a Rust type because it is not of the expected GreyCat type
+    ValueMismatch { expected: String, got: String },
+    /// An error raised by a [`Library`](crate::library::Library), a loader or a `serde` impl
+    Custom(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// Wraps any error raised by a [`Library`](crate::library::Library) or a loader
+    pub fn custom<E>(error: E) -> Self
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Self::Custom(error.into())
+    }
+
+    /// Returns the offset of the stream at which the error was detected, if known
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Error::UnexpectedEof { offset }
+            | Error::InvalidUtf8 { offset }
+            | Error::InvalidChar { offset, .. }
+            | Error::UnknownPrimitive { offset, .. }
+            | Error::UnknownType { offset, .. }
+            | Error::UnknownSymbolId { offset, .. }
+            | Error::UnknownFunctionId { offset, .. }
+            | Error::UnknownEnumVariant { offset, .. }
+            | Error::Unsupported { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Sets the offset of the error, unless it is already known
+    pub(crate) fn at(mut self, at: u64) -> Self {
+        match &mut self {
+            Error::UnexpectedEof { offset }
+            | Error::InvalidUtf8 { offset }
+            | Error::InvalidChar { offset, .. }
+            | Error::UnknownPrimitive { offset, .. }
+            | Error::UnknownType { offset, .. }
+            | Error::UnknownSymbolId { offset, .. }
+            | Error::UnknownFunctionId { offset, .. }
+            | Error::UnknownEnumVariant { offset, .. }
+            | Error::Unsupported { offset, .. } => {
+                offset.get_or_insert(at);
+            }
+            _ => (),
+        }
+        self
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => err.fmt(f)?,
+            Error::UnexpectedEof { .. } => write!(f, "unexpected end of stream")?,
+            Error::InvalidUtf8 { .. } => write!(f, "invalid UTF-8 string")?,
+            Error::InvalidChar { code, .. } => write!(f, "invalid char {code:#x}")?,
+            Error::UnknownPrimitive { header, .. } => write!(f, "unknown primitive type {header}")?,
+            Error::UnknownType { id, .. } => write!(f, "unknown type with id '{id}'")?,
+            Error::UnknownSymbolId { id, .. } => write!(f, "unknown symbol with id '{id}'")?,
+            Error::UnknownFunctionId { id, .. } => write!(f, "unknown function with id '{id}'")?,
+            Error::UnknownSymbol { symbol } => write!(f, "unknown symbol '{symbol}'")?,
+            Error::UnknownEnumVariant { ty, variant, .. } => {
+                write!(f, "enum '{ty}' has no variant at offset {variant}")?
+            }
+            Error::Unsupported { ty, .. } => write!(f, "unsupported type \"{ty}\"")?,
+            Error::AttrTypeMismatch {
+                ty,
+                attr,
+                expected,
+                got,
+            } => write!(
+                f,
+                "expected attribute '{attr}' in '{ty}' to be {expected}, got {got}"
+            )?,
+            Error::AttrCountMismatch { ty, expected, got } => write!(
+                f,
+                "object '{ty}' has {expected} attributes defined but {got} values"
+            )?,
+            Error::AbiMismatch(err) => err.fmt(f)?,
+            Error::ArgCount {
+                function,
+                expected,
+                got,
+            } => write!(f, "`{function}` expects {expected} arguments, got {got}")?,
+            Error::InvalidArgs(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    err.fmt(f)?;
+                }
+            }
+            Error::InvalidAbi { reason } => write!(f, "invalid ABI: {reason}")?,
+            Error::NotConvertible { value } => {
+                write!(f, "unable to convert {value} to the reader ABI")?
+            }
+            Error::UnknownTypeName { fqn } => write!(f, "unknown type '{fqn}'")?,
+            Error::UnknownFunction { fqn } => write!(f, "unknown function '{fqn}'")?,
+            Error::UnknownAttr { ty, attr } => write!(f, "type '{ty}' has no attribute '{attr}'")?,
+            Error::UnknownEnumKey { ty, key } => write!(f, "enum '{ty}' has no variant '{key}'")?,
+            Error::NotAnEnum { ty } => write!(f, "type '{ty}' is not an enum")?,
+            Error::MissingAttrs { ty, attrs } => {
+                write!(f, "missing attributes for '{ty}': {}", attrs.join(", "))?
+            }
+            Error::ValueMismatch { expected, got } => write!(f, "expected {expected}, got {got}")?,
+            Error::Custom(err) => err.fmt(f)?,
+        }
+        if let Some(offset) = self.offset() {
+            write!(f, " (at byte {offset})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::AbiMismatch(err) => Some(err),
+            Error::Custom(err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+/// An argument of a function call that does not match its parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgError {
+    pub function: String,
+    pub param: String,
+    /// the path of the violation is relative to the argument
+    pub violation: Violation,
+}
+
+impl std::fmt::Display for ArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            function,
+            param,
+            violation,
+        } = self;
+        write!(f, "param `{param}` of `{function}` ")?;
+        if !violation.path.is_empty() {
+            write!(f, "at `{}` ", violation.path)?;
+        }
+        violation.kind.fmt(f)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof { offset: None },
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<AbiMismatch> for Error {
+    fn from(err: AbiMismatch) -> Self {
+        Error::AbiMismatch(err)
+    }
+}
+
+/// A reader that counts the bytes read, so that errors can tell where they happened
+pub(crate) struct Tracked<R> {
+    inner: R,
+    offset: u64,
+}
+
+impl<R: Read> Tracked<R> {
+    /// Calls `f` with the tracked `reader` and sets the offset of the error it returns, if any
+    pub(crate) fn run<T>(reader: R, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let mut tracked = Self {
+            inner: reader,
+            offset: 0,
+        };
+        f(&mut tracked).map_err(|err| err.at(tracked.offset))
+    }
+}
+
+impl<R: Read> Read for Tracked<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.offset += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::serialize::AbiSerialize;
+    use crate::value::{HeaderValue, Value};
+
+    #[test]
+    fn decode_errors_carry_offset() {
+        let abi = project_abi();
+        let mut bytes = Vec::new();
+        abi.headers.headers.write_to(&mut bytes, &abi).unwrap();
+        Value::String("hello".into())
+            .write_to(&mut bytes, &abi)
+            .unwrap();
+
+        let truncated = &bytes[..bytes.len() - 2];
+        let err = HeaderValue::from_reader(truncated, &abi).err().unwrap();
+        assert!(
+            matches!(err, Error::UnexpectedEof { offset: Some(offset) } if offset == truncated.len() as u64)
+        );
+
+        // 8 bytes of headers, the OBJECT header, then an unknown type id
+        let mut bytes = bytes[..8].to_vec();
+        bytes.extend([crate::primitive::OBJECT, 42]);
+        let err = HeaderValue::from_reader(&bytes[..], &abi).err().unwrap();
+        assert!(matches!(
+            err,
+            Error::UnknownType {
+                id: 42,
+                offset: Some(10)
+            }
+        ));
+        assert_eq!(err.to_string(), "unknown type with id '42' (at byte 10)");
+    }
+}
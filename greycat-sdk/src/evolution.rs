@@ -0,0 +1,514 @@
+//! Decoding of values written under an older (or newer) ABI.
+//!
+//! Types, attributes, enum variants and symbols are mapped from the writer ABI to the reader ABI
+//! by name. Attributes that do not exist in the writer ABI, or whose values cannot be converted
+//! to the reader attribute type, are filled with `null` and reported by [`AbiEvolution::issues`].
+
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::abi::{Abi, AbiAttr, AbiType};
+use crate::deserialize::AbiDeserialize;
+use crate::error::{Error, Result, Tracked};
+use crate::gc_enum::GcEnum;
+use crate::gc_object::GcObject;
+use crate::primitive;
+use crate::std_n::core::{Table, Tuple};
+use crate::value::Value;
+
+/// Something in the writer data that cannot be faithfully represented with the reader ABI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvolIssue {
+    /// The type does not exist in the reader ABI, values of that type are read as `null`
+    TypeRemoved { ty: String },
+    /// The enum variant does not exist in the reader ABI, it is read as `null`
+    EnumVariantRemoved { ty: String, variant: String },
+    /// The non-nullable reader attribute does not exist in the writer ABI, it is read as `null`
+    AttrMissing { ty: String, attr: String },
+    /// The attribute type changed and the writer values cannot be converted, they are read as `null`
+    AttrNotConvertible {
+        ty: String,
+        attr: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl std::fmt::Display for EvolIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvolIssue::TypeRemoved { ty } => write!(f, "type '{ty}' does not exist anymore"),
+            EvolIssue::EnumVariantRemoved { ty, variant } => {
+                write!(f, "enum variant '{ty}::{variant}' does not exist anymore")
+            }
+            EvolIssue::AttrMissing { ty, attr } => {
+                write!(f, "non-nullable attribute '{ty}.{attr}' has no value")
+            }
+            EvolIssue::AttrNotConvertible { ty, attr, from, to } => write!(
+                f,
+                "attribute '{ty}.{attr}' cannot be converted from '{from}' to '{to}'"
+            ),
+        }
+    }
+}
+
+/// How a writer type maps to a reader type
+struct TypeMapping {
+    /// the reader type
+    ty: Arc<AbiType>,
+    /// for each reader attribute, the offset of the writer attribute with the same name
+    attrs: Vec<Option<usize>>,
+}
+
+/// Maps the values decoded with a writer ABI to the types of a reader ABI
+pub struct AbiEvolution<'w, 'r> {
+    writer: &'w Abi,
+    reader: &'r Abi,
+    /// indexed by writer type offset
+    types: Vec<Option<TypeMapping>>,
+    issues: Vec<EvolIssue>,
+}
+
+impl<'w, 'r> AbiEvolution<'w, 'r> {
+    pub fn new(writer: &'w Abi, reader: &'r Abi) -> Self {
+        let mut issues = Vec::new();
+        let mut reported = BTreeSet::new();
+        let mut types = Vec::with_capacity(writer.types.len());
+
+        for w_ty in writer.types.iter() {
+            let fqn = w_ty.named_fqn(writer);
+            let Some(r_ty) = reader.get_type_by_fqn(&fqn) else {
+                if reported.insert(fqn.clone()) {
+                    issues.push(EvolIssue::TypeRemoved { ty: fqn });
+                }
+                types.push(None);
+                continue;
+            };
+
+            let w_attrs = w_ty.attrs.as_deref().unwrap_or_default();
+            let r_attrs = r_ty.attrs.as_deref().unwrap_or_default();
+            let attrs: Vec<Option<usize>> = r_attrs
+                .iter()
+                .map(|r_attr| {
+                    let name = &reader.symbols[r_attr.name];
+                    w_attrs
+                        .iter()
+                        .position(|w_attr| &writer.symbols[w_attr.name] == name)
+                })
+                .collect();
+
+            // masked types share their name with the program type, only report once
+            if reported.insert(fqn.clone()) {
+                if r_ty.is_enum {
+                    for w_attr in w_attrs {
+                        let variant = &writer.symbols[w_attr.name];
+                        if !r_attrs
+                            .iter()
+                            .any(|r_attr| &reader.symbols[r_attr.name] == variant)
+                        {
+                            issues.push(EvolIssue::EnumVariantRemoved {
+                                ty: fqn.clone(),
+                                variant: variant.to_string(),
+                            });
+                        }
+                    }
+                } else {
+                    for (r_attr, w_offset) in r_attrs.iter().zip(attrs.iter()) {
+                        let attr = reader.symbols[r_attr.name].to_string();
+                        match w_offset {
+                            None if !r_attr.nullable => issues.push(EvolIssue::AttrMissing {
+                                ty: fqn.clone(),
+                                attr,
+                            }),
+                            None => (),
+                            Some(w_offset) => {
+                                let w_attr = &w_attrs[*w_offset];
+                                let from = attr_type_fqn(w_attr, writer);
+                                let to = attr_type_fqn(r_attr, reader);
+                                if !is_convertible(&from, &to) {
+                                    issues.push(EvolIssue::AttrNotConvertible {
+                                        ty: fqn.clone(),
+                                        attr,
+                                        from,
+                                        to,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            types.push(Some(TypeMapping { ty: r_ty, attrs }));
+        }
+
+        Self {
+            writer,
+            reader,
+            types,
+            issues,
+        }
+    }
+
+    /// Returns everything that cannot be faithfully read using the reader ABI
+    pub fn issues(&self) -> &[EvolIssue] {
+        &self.issues
+    }
+
+    /// Reads a value written using the writer ABI and converts it to the reader ABI
+    pub fn read_value<R: Read>(&self, reader: &mut R) -> Result<Value<'r>> {
+        let value = Tracked::run(reader, |reader| reader.read_value(self.writer))?;
+        self.convert(value)
+    }
+
+    /// Converts a value decoded using the writer ABI to the reader ABI.
+    ///
+    /// Nested values that cannot be converted are replaced by `null`, but this fails if the
+    /// given `value` itself cannot be converted.
+    pub fn convert(&self, value: Value<'w>) -> Result<Value<'r>> {
+        self.convert_value(value)
+            .map_err(|value| Error::NotConvertible {
+                value: value.type_name(self.writer),
+            })
+    }
+
+    /// Converts `value` or gives it back if it cannot be converted
+    fn convert_value(&self, value: Value<'w>) -> std::result::Result<Value<'r>, Value<'w>> {
+        let value = match value {
+            Value::Null => Value::Null,
+            Value::Int(v) => Value::Int(v),
+            Value::Float(v) => Value::Float(v),
+            Value::Char(v) => Value::Char(v),
+            Value::Bool(v) => Value::Bool(v),
+            Value::Node(v) => Value::Node(v),
+            Value::NodeTime(v) => Value::NodeTime(v),
+            Value::NodeIndex(v) => Value::NodeIndex(v),
+            Value::NodeList(v) => Value::NodeList(v),
+            Value::NodeGeo(v) => Value::NodeGeo(v),
+            Value::Geo(v) => Value::Geo(v),
+            Value::Time(v) => Value::Time(v),
+            Value::Duration(v) => Value::Duration(v),
+            Value::String(v) => Value::String(v),
+            Value::Symbol(v) => match self.reader.get_symbol(v.0) {
+                Some(symbol) => Value::Symbol(symbol),
+                None => Value::String(v.0.to_string()),
+            },
+            Value::Array(values) => Value::Array(
+                values
+                    .into_iter()
+                    .map(|v| self.convert_value(v).unwrap_or_default())
+                    .collect(),
+            ),
+            Value::Map(map) => Value::Map(
+                map.into_iter()
+                    .filter_map(|(k, v)| {
+                        Some((
+                            self.convert_value(k).ok()?,
+                            self.convert_value(v).unwrap_or_default(),
+                        ))
+                    })
+                    .collect(),
+            ),
+            Value::Enum(en) => match self.convert_enum(&en) {
+                Some(en) => Value::Enum(en),
+                None => return Err(Value::Enum(en)),
+            },
+            Value::Obj(obj) => match self.mapping(&obj.ty) {
+                Some(mapping) => Value::Obj(self.convert_object(obj, mapping)),
+                None => return Err(Value::Obj(obj)),
+            },
+            Value::Table(table) => Value::Table(Box::new(Table {
+                cols: table.cols,
+                values: table
+                    .values
+                    .into_iter()
+                    .map(|v| self.convert_value(v).unwrap_or_default())
+                    .collect(),
+            })),
+            Value::Tensor(v) => Value::Tensor(v),
+            Value::Buffer(v) => Value::Buffer(v),
+            Value::Tuple(tuple) => Value::Tuple(Box::new(Tuple {
+                x: self.convert_value(tuple.x).unwrap_or_default(),
+                y: self.convert_value(tuple.y).unwrap_or_default(),
+            })),
+            Value::Error(v) => Value::Error(v),
+            Value::Function(v) => {
+                let fqn = v.function.named_fqn(self.writer);
+                match self.reader.fn_value(&fqn) {
+                    Ok(function) => Value::Function(function),
+                    Err(_) => return Err(Value::Function(v)),
+                }
+            }
+            Value::Set(values) => Value::Set(
+                values
+                    .into_iter()
+                    .filter_map(|v| self.convert_value(v).ok())
+                    .collect(),
+            ),
+        };
+        Ok(value)
+    }
+
+    /// Returns how the writer type `ty` maps to the reader ABI, if it still exists
+    fn mapping(&self, ty: &AbiType) -> Option<&TypeMapping> {
+        self.types.get(ty.offset as usize)?.as_ref()
+    }
+
+    fn convert_enum(&self, en: &GcEnum<'w>) -> Option<GcEnum<'r>> {
+        let mapping = self.mapping(&en.ty)?;
+        let attrs = mapping.ty.attrs.as_deref()?;
+        let attr = attrs
+            .iter()
+            .find(|attr| &self.reader.symbols[attr.name] == en.key)?;
+        Some(GcEnum {
+            ty: mapping.ty.clone(),
+            module: &self.reader.symbols[mapping.ty.module],
+            name: &self.reader.symbols[mapping.ty.name],
+            key: &self.reader.symbols[attr.name],
+            offset: attr.mapped_att_offset,
+        })
+    }
+
+    fn convert_object(&self, obj: GcObject<'w>, mapping: &TypeMapping) -> GcObject<'r> {
+        let Some(mut w_values) = obj.values else {
+            return GcObject {
+                ty: mapping.ty.clone(),
+                values: None,
+            };
+        };
+        let r_attrs = mapping.ty.attrs.as_deref().unwrap_or_default();
+
+        let values = r_attrs
+            .iter()
+            .zip(mapping.attrs.iter())
+            .map(|(r_attr, w_offset)| {
+                let value = w_offset
+                    .and_then(|offset| w_values.get_mut(offset))
+                    .map(std::mem::take)
+                    .and_then(|value| self.convert_value(value).ok())
+                    .unwrap_or_default();
+                self.fit(value, r_attr)
+            })
+            .collect::<Vec<_>>();
+
+        GcObject {
+            ty: mapping.ty.clone(),
+            values: Some(values.into_boxed_slice()),
+        }
+    }
+
+    /// Converts `value` to the type expected by `attr` or returns `null`
+    fn fit(&self, value: Value<'r>, attr: &AbiAttr) -> Value<'r> {
+        match (attr.sbi_type, value) {
+            (_, Value::Null) => Value::Null,
+            (primitive::FLOAT, Value::Int(v)) => Value::Float((v as f64).into()),
+            (primitive::UNDEFINED | primitive::NULL, v) => v,
+            (primitive::BOOL, v @ Value::Bool(_))
+            | (primitive::CHAR, v @ Value::Char(_))
+            | (primitive::INT, v @ Value::Int(_))
+            | (primitive::FLOAT, v @ Value::Float(_))
+            | (primitive::NODE, v @ Value::Node(_))
+            | (primitive::NODE_TIME, v @ Value::NodeTime(_))
+            | (primitive::NODE_INDEX, v @ Value::NodeIndex(_))
+            | (primitive::NODE_LIST, v @ Value::NodeList(_))
+            | (primitive::NODE_GEO, v @ Value::NodeGeo(_))
+            | (primitive::GEO, v @ Value::Geo(_))
+            | (primitive::TIME, v @ Value::Time(_))
+            | (primitive::DURATION, v @ Value::Duration(_))
+            | (primitive::ENUM, v @ Value::Enum(_))
+            | (primitive::FN, v @ Value::Function(_)) => v,
+            (primitive::OBJECT, v) => {
+                let expected = attr.prog_type(self.reader);
+                let fits = match (&v, expected) {
+                    (_, None) => true,
+                    (_, Some(ty)) if ty.is_abstract => true,
+                    (Value::Obj(obj), Some(ty)) => obj.ty.is_subtype_of(&ty),
+                    (Value::Array(_), Some(ty)) => ty.offset == self.reader.types.core.array,
+                    (Value::Map(_), Some(ty)) => ty.offset == self.reader.types.core.map,
+                    (Value::String(_) | Value::Symbol(_), Some(ty)) => {
+                        ty.offset == self.reader.types.core.string
+                    }
+                    (Value::Enum(en), Some(ty)) => en.ty.offset == ty.offset,
+                    _ => false,
+                };
+                if fits {
+                    v
+                } else {
+                    Value::Null
+                }
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+fn attr_type_fqn(attr: &AbiAttr, abi: &Abi) -> String {
+    match attr.prog_type(abi) {
+        Some(ty) => ty.named_fqn(abi),
+        None => format!("Type#{}", attr.prog_type_offset),
+    }
+}
+
+fn is_convertible(from: &str, to: &str) -> bool {
+    from == to || to == "core::any" || (from == "core::int" && to == "core::float")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::{attr, project_abi, rebuild_abi, ty};
+    use crate::abi::{AbiFunctions, AbiSymbols, AbiTypes};
+    use crate::serialize::AbiSerialize;
+
+    #[test]
+    fn read_with_evolved_abi() {
+        let writer = project_abi();
+        // Person { email: String?; age: float; } and Status::Inactive is removed
+        let reader = rebuild_abi(&writer, &["email"], |name, attrs| match name {
+            "Person" => {
+                let mut age = attrs.pop().unwrap();
+                age.sbi_type = primitive::FLOAT;
+                let mut email = attrs.pop().unwrap();
+                email.name = 17;
+                email.nullable = true;
+                *attrs = vec![email, age];
+            }
+            "Status" => {
+                attrs.pop();
+            }
+            _ => (),
+        });
+
+        let evol = AbiEvolution::new(&writer, &reader);
+        assert_eq!(
+            evol.issues(),
+            &[EvolIssue::EnumVariantRemoved {
+                ty: "project::Status".to_string(),
+                variant: "Inactive".to_string()
+            }]
+        );
+
+        let person = GcObject::new(
+            writer.get_type_by_fqn("project::Person").unwrap(),
+            Some([Value::String("John".to_string()), Value::Int(42)]),
+        );
+        let mut bytes = Vec::new();
+        person.write_to(&mut bytes, &writer).unwrap();
+
+        let value = evol.read_value(&mut &bytes[..]).unwrap();
+        let Value::Obj(person) = value else {
+            panic!("expected an object, got {value:?}");
+        };
+        assert_eq!(person.ty.named_fqn(&reader), "project::Person");
+        assert_eq!(person.get_value(0), Some(&Value::Null));
+        assert_eq!(person.get_value(1), Some(&Value::Float(42.0.into())));
+    }
+
+    #[test]
+    fn read_with_reordered_and_removed_attrs() {
+        let abi = project_abi();
+        // Person { name: String; age: int; email: String?; }
+        let writer = rebuild_abi(&abi, &["email"], |name, attrs| {
+            if name == "Person" {
+                let mut email = attr(17, 1, 2, primitive::OBJECT);
+                email.nullable = true;
+                attrs.push(email);
+            }
+        });
+        // Person { age: int; name: String; }
+        let reader = rebuild_abi(&abi, &[], |name, attrs| {
+            if name == "Person" {
+                attrs.reverse();
+            }
+        });
+
+        let evol = AbiEvolution::new(&writer, &reader);
+        assert_eq!(evol.issues(), &[]);
+
+        for email in [Value::String("john@doe.com".to_string()), Value::Null] {
+            let person = GcObject::new(
+                writer.get_type_by_fqn("project::Person").unwrap(),
+                Some([Value::String("John".to_string()), Value::Int(42), email]),
+            );
+            let mut bytes = Vec::new();
+            person.write_to(&mut bytes, &writer).unwrap();
+
+            let value = evol.read_value(&mut &bytes[..]).unwrap();
+            let Value::Obj(person) = value else {
+                panic!("expected an object, got {value:?}");
+            };
+            assert_eq!(person.get_value(0), Some(&Value::Int(42)));
+            assert_eq!(
+                person.get_value(1),
+                Some(&Value::String("John".to_string()))
+            );
+            assert_eq!(person.get_value(2), None);
+        }
+    }
+
+    #[test]
+    fn convert_keeps_subtypes() {
+        let abi = project_abi();
+        let symbols = AbiSymbols::new(
+            abi.symbols
+                .iter()
+                .skip(1)
+                .chain(["boss", "Employee", "salary"]),
+        );
+        let boss = symbols.get("boss").unwrap();
+        let employee = symbols.get("Employee").unwrap();
+        let salary = symbols.get("salary").unwrap();
+        let mut types: Vec<_> = abi.types.iter().map(|ty| AbiType::clone(ty)).collect();
+        // Person { name: String; age: int; boss: Person?; }
+        let mut boss = attr(boss, 4, 2, primitive::OBJECT);
+        boss.nullable = true;
+        let person = &mut types[4];
+        person.nullable_nb_bytes = 1;
+        person.attrs = Some(
+            [person.attrs.as_deref().unwrap(), &[boss]]
+                .concat()
+                .into_boxed_slice(),
+        );
+        // Employee extends Person { salary: int; }
+        let mut attrs = Vec::from(person.attrs.as_deref().unwrap());
+        attrs.push(attr(salary, 0, 3, primitive::INT));
+        types.push(AbiType {
+            lib_name: 3,
+            nullable_nb_bytes: 1,
+            ..ty(6, 3, employee, 0, Some(attrs))
+        });
+        let types = AbiTypes::new(types.into_iter().map(Arc::new).collect(), &symbols);
+        let functions = AbiFunctions::new(abi.functions.to_vec());
+        let abi = Abi::from_parts(abi.headers, symbols, types, functions, None).unwrap();
+
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+        let employee = abi.get_type_by_fqn("project::Employee").unwrap();
+        assert!(employee.is_subtype_of(&person));
+        assert!(!person.is_subtype_of(&employee));
+
+        let boss = GcObject::new(
+            employee,
+            Some([
+                Value::String("Jane".to_string()),
+                Value::Int(51),
+                Value::Null,
+                Value::Int(100),
+            ]),
+        );
+        let person = GcObject::new(
+            person,
+            Some([
+                Value::String("John".to_string()),
+                Value::Int(42),
+                Value::Obj(boss.clone()),
+            ]),
+        );
+
+        let evol = AbiEvolution::new(&abi, &abi);
+        let Value::Obj(person) = evol.convert(Value::Obj(person)).unwrap() else {
+            panic!("expected an object");
+        };
+        assert_eq!(person.get_value(2), Some(&Value::Obj(boss)));
+    }
+}
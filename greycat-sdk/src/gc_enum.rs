@@ -1,20 +1,79 @@
-use std::rc::Rc;
-use std::io::Write;
+use crate::error::{Error, Result};
 use byteorder::WriteBytesExt;
-use anyhow::Result;
+use std::io::Write;
+use std::sync::Arc;
 
 use crate::abi::{Abi, AbiType};
-use crate::serialize::*;
 use crate::primitive;
+use crate::serialize::*;
 use crate::varint::*;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct GcEnum<'abi> {
-    pub ty: Rc<AbiType>,
+    pub ty: Arc<AbiType>,
+    /// the module of the enum
+    pub module: &'abi str,
+    /// the name of the enum
+    pub name: &'abi str,
     pub offset: u32,
     pub key: &'abi str,
 }
 
+impl<'abi> GcEnum<'abi> {
+    /// Returns the variants of the enum `ty`, in order
+    pub fn variants(ty: &Arc<AbiType>, abi: &'abi Abi) -> impl Iterator<Item = GcEnum<'abi>> {
+        let ty = Arc::clone(ty);
+        let nb_variants = ty.attrs.as_deref().map_or(0, <[_]>::len);
+        (0..nb_variants).map(move |i| {
+            let attr = &ty.attrs.as_deref().unwrap_or_default()[i];
+            GcEnum {
+                ty: Arc::clone(&ty),
+                module: &abi.symbols[ty.module],
+                name: &abi.symbols[ty.name],
+                offset: attr.mapped_att_offset,
+                key: &abi.symbols[attr.name],
+            }
+        })
+    }
+}
+
+impl Abi {
+    /// Returns the variant `key` of the enum `fqn`, eg. `abi.enum_value("core::TimeZone", "Europe_Paris")`
+    pub fn enum_value(&self, fqn: &str, key: &str) -> Result<GcEnum<'_>> {
+        self.enum_variants(fqn)?
+            .find(|variant| variant.key == key)
+            .ok_or_else(|| Error::UnknownEnumKey {
+                ty: fqn.to_string(),
+                key: key.to_string(),
+            })
+    }
+
+    /// Returns the variants of the enum `fqn`, in order
+    pub fn enum_variants(&self, fqn: &str) -> Result<impl Iterator<Item = GcEnum<'_>>> {
+        let ty = self
+            .get_type_by_fqn(fqn)
+            .ok_or_else(|| Error::UnknownTypeName {
+                fqn: fqn.to_string(),
+            })?;
+        if !ty.is_enum {
+            return Err(Error::NotAnEnum {
+                ty: fqn.to_string(),
+            });
+        }
+        Ok(GcEnum::variants(&ty, self))
+    }
+
+    /// Parses a `"<module>::<enum>::<variant>"` string, eg. `"core::TimeZone::Europe_Paris"`
+    pub fn parse_enum(&self, value: &str) -> Result<GcEnum<'_>> {
+        match value.rsplit_once("::") {
+            Some((fqn, key)) => self.enum_value(fqn, key),
+            None => Err(Error::UnknownTypeName {
+                fqn: value.to_string(),
+            }),
+        }
+    }
+}
+
 impl std::hash::Hash for GcEnum<'_> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.ty.mapped_abi_type_offset.hash(state);
@@ -22,16 +81,13 @@ impl std::hash::Hash for GcEnum<'_> {
     }
 }
 
+/// Serializes the fqn of the variant, eg. `"core::TimeZone::Europe_Paris"`
 impl serde::Serialize for GcEnum<'_> {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut fqn = self.ty.fqn();
-        fqn.push_str("::");
-        fqn.push_str(self.key);
-
-        serializer.serialize_str(&fqn)
+        serializer.collect_str(self)
     }
 }
 
@@ -49,8 +105,54 @@ impl AbiSerialize for GcEnum<'_> {
     }
 }
 
+/// Displays `"<module>::<enum>::<variant>"`, as parsed by [`Abi::parse_enum`]
+impl std::fmt::Display for GcEnum<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}::{}::{}", self.module, self.name, self.key)
+    }
+}
+
 impl std::fmt::Debug for GcEnum<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}::{}", self.ty.fqn(), self.key)
+        write!(f, "{self}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+
+    #[test]
+    fn enum_values_by_name() {
+        let abi = project_abi();
+
+        let inactive = abi.enum_value("project::Status", "Inactive").unwrap();
+        assert_eq!((inactive.offset, inactive.key), (1, "Inactive"));
+        assert_eq!(
+            abi.parse_enum("project::Status::Inactive").unwrap(),
+            inactive
+        );
+        assert_eq!(
+            serde_json::to_value(&inactive).unwrap(),
+            "project::Status::Inactive"
+        );
+        assert_eq!(format!("{inactive:?}"), "project::Status::Inactive");
+
+        let keys: Vec<_> = abi
+            .enum_variants("project::Status")
+            .unwrap()
+            .map(|variant| variant.key)
+            .collect();
+        assert_eq!(keys, ["Active", "Inactive"]);
+
+        assert!(matches!(
+            abi.enum_value("project::Status", "Unknown"),
+            Err(Error::UnknownEnumKey { .. })
+        ));
+        assert!(matches!(
+            abi.parse_enum("project::Person::name"),
+            Err(Error::NotAnEnum { .. })
+        ));
     }
 }
@@ -0,0 +1,158 @@
+use byteorder::WriteBytesExt;
+use std::io::Write;
+
+use crate::abi::{Abi, AbiFn};
+use crate::error::{Error, Result};
+use crate::primitive;
+use crate::serialize::*;
+use crate::varint::*;
+
+/// A reference to a function of the ABI, eg. a task callback or a sort comparator
+#[derive(Clone, Copy)]
+pub struct GcFn<'abi> {
+    /// the offset of the function in the ABI
+    pub offset: u32,
+    pub function: &'abi AbiFn,
+    pub module: &'abi str,
+    /// the type of a type method
+    pub ty: Option<&'abi str>,
+    pub name: &'abi str,
+}
+
+impl<'abi> GcFn<'abi> {
+    /// Returns the function at `offset` in the ABI, if any
+    pub fn new(abi: &'abi Abi, offset: u32) -> Option<Self> {
+        let function = abi.functions.get(offset as usize)?;
+        Some(Self {
+            offset,
+            function,
+            module: &abi.symbols[function.module],
+            ty: function.r#type.map(|ty| &abi.symbols[ty]),
+            name: &abi.symbols[function.name],
+        })
+    }
+
+    /// Returns `"<module>::<name>"` or, for type methods, `"<module>::<type>::<name>"`
+    pub fn fqn(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Abi {
+    /// Returns a reference to the function `"<module>::<name>"` or, for type methods,
+    /// `"<module>::<type>::<name>"`, eg. `abi.fn_value("project::add")`
+    pub fn fn_value(&self, fqn: &str) -> Result<GcFn<'_>> {
+        self.get_fn_offset_by_fqn(fqn)
+            .and_then(|offset| GcFn::new(self, offset))
+            .ok_or_else(|| Error::UnknownFunction {
+                fqn: fqn.to_string(),
+            })
+    }
+}
+
+impl PartialEq for GcFn<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl Eq for GcFn<'_> {}
+
+impl PartialOrd for GcFn<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for GcFn<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.offset.cmp(&other.offset)
+    }
+}
+
+impl std::hash::Hash for GcFn<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.offset.hash(state);
+    }
+}
+
+/// Serializes the fqn of the function, eg. `"project::add"`
+impl serde::Serialize for GcFn<'_> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl AbiSerialize for GcFn<'_> {
+    fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        writer.write_u8(primitive::FN)?;
+        let n = self.write_raw_to(writer, abi)?;
+        Ok(1 + n)
+    }
+
+    fn write_raw_to<W: Write>(&self, writer: &mut W, _abi: &Abi) -> Result<usize> {
+        let n = writer.write_vu32(self.offset)?;
+        Ok(n)
+    }
+}
+
+impl std::fmt::Display for GcFn<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.ty {
+            Some(ty) => write!(f, "{}::{ty}::{}", self.module, self.name),
+            None => write!(f, "{}::{}", self.module, self.name),
+        }
+    }
+}
+
+impl std::fmt::Debug for GcFn<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "fn {self}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::deserialize::AbiDeserialize;
+    use crate::value::Value;
+
+    #[test]
+    fn fn_values_round_trip() {
+        let abi = project_abi();
+
+        let add = abi.fn_value("project::add").unwrap();
+        assert_eq!((add.offset, add.name), (0, "add"));
+        assert_eq!(add.function.params.len(), 2);
+        assert!(matches!(
+            abi.fn_value("project::sub"),
+            Err(Error::UnknownFunction { .. })
+        ));
+
+        let value = Value::Function(add);
+        let mut bytes = Vec::new();
+        let n = value.write_to(&mut bytes, &abi).unwrap();
+        assert_eq!(bytes, [primitive::FN, 0]);
+        assert_eq!(n, bytes.len());
+        assert_eq!((&*bytes).read_value(&abi).unwrap(), value);
+        assert_eq!(value.display(&abi).to_string(), "fn project::add");
+        assert_eq!(value.to_json(&abi).to_string(), "\"project::add\"");
+        assert_eq!(format!("{value:?}"), "fn project::add");
+        assert_eq!(format!("{add:?}"), "fn project::add");
+        assert_eq!(serde_json::to_string(&add).unwrap(), "\"project::add\"");
+        let method = GcFn {
+            ty: Some("Person"),
+            ..add
+        };
+        assert_eq!(format!("{method:?}"), "fn project::Person::add");
+
+        assert!(matches!(
+            (&[primitive::FN, 1][..]).read_value(&abi),
+            Err(Error::UnknownFunctionId { id: 1, .. })
+        ));
+    }
+}
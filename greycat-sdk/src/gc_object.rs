@@ -1,10 +1,9 @@
-use anyhow::Result;
+use crate::error::{Error, Result};
 use byteorder::WriteBytesExt;
-use std::cell::{Ref, RefCell};
 use std::io::Write;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use crate::abi::{Abi, AbiType};
+use crate::abi::{Abi, AbiAttr, AbiType};
 // use crate::deserialize::AbiDeserialize;
 // use crate::prelude::TypeLoader;
 use crate::primitive;
@@ -14,15 +13,15 @@ use crate::varint::*;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct GcObject<'abi> {
-    pub ty: Rc<AbiType>,
-    pub values: Option<RefCell<Box<[Value<'abi>]>>>,
+    pub ty: Arc<AbiType>,
+    pub values: Option<Box<[Value<'abi>]>>,
 }
 
 impl<'abi> GcObject<'abi> {
-    pub fn new<T: Into<Box<[Value<'abi>]>>>(ty: Rc<AbiType>, values: Option<T>) -> Self {
+    pub fn new<T: Into<Box<[Value<'abi>]>>>(ty: Arc<AbiType>, values: Option<T>) -> Self {
         Self {
             ty,
-            values: values.map(|values| RefCell::new(values.into())),
+            values: values.map(Into::into),
         }
     }
 
@@ -30,24 +29,156 @@ impl<'abi> GcObject<'abi> {
     ///
     /// This method can panic if the given `index` is not in the bounds of the instance
     /// values.
-    pub fn set_value(&self, index: usize, value: Value<'abi>) {
-        if let Some(values) = &self.values {
-            let mut values = values.borrow_mut();
+    pub fn set_value(&mut self, index: usize, value: Value<'abi>) {
+        if let Some(values) = &mut self.values {
             values[index] = value;
         }
     }
 
-    pub fn get_value(&self, index: usize) -> Option<RefValue<'_, 'abi>> {
-        if let Some(values) = &self.values {
-            let values = values.borrow();
-            let value = RefValue {
-                inner: values,
-                index,
-            };
-            Some(value)
-        } else {
-            None
+    pub fn get_value(&self, index: usize) -> Option<&Value<'abi>> {
+        self.values.as_ref().and_then(|values| values.get(index))
+    }
+
+    /// Returns the value of the attribute `name`
+    pub fn get(&self, name: &str, abi: &Abi) -> Result<&Value<'abi>> {
+        let offset = self.attr_offset(name, abi)?;
+        self.get_value(offset)
+            .ok_or_else(|| self.count_mismatch(abi))
+    }
+
+    /// Replaces the value of the attribute `name`
+    pub fn set(&mut self, name: &str, value: Value<'abi>, abi: &Abi) -> Result<()> {
+        let offset = self.attr_offset(name, abi)?;
+        match self
+            .values
+            .as_deref_mut()
+            .and_then(|values| values.get_mut(offset))
+        {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(self.count_mismatch(abi)),
+        }
+    }
+
+    /// Returns the name and value of each attribute, in order
+    pub fn iter_named<'a>(
+        &'a self,
+        abi: &'a Abi,
+    ) -> Result<impl Iterator<Item = (&'a str, &'a Value<'abi>)>> {
+        let attrs = self.ty.attrs.as_deref().unwrap_or_default();
+        let values = self.values.as_deref().unwrap_or_default();
+        if attrs.len() != values.len() {
+            return Err(self.count_mismatch(abi));
+        }
+        Ok(attrs
+            .iter()
+            .zip(values)
+            .map(|(attr, value)| (&abi.symbols[attr.name], value)))
+    }
+
+    fn attr_offset(&self, name: &str, abi: &Abi) -> Result<usize> {
+        self.ty
+            .attr_offset(name, abi)
+            .ok_or_else(|| Error::UnknownAttr {
+                ty: self.ty.named_fqn(abi),
+                attr: name.to_string(),
+            })
+    }
+
+    fn count_mismatch(&self, abi: &Abi) -> Error {
+        Error::AttrCountMismatch {
+            ty: self.ty.named_fqn(abi),
+            expected: self.ty.attrs.as_deref().map_or(0, <[_]>::len),
+            got: self.values.as_deref().map_or(0, <[_]>::len),
+        }
+    }
+
+    /// Starts building an object of type `fqn` by setting its attributes by name, see [`GcObjectBuilder`]
+    pub fn builder(abi: &'abi Abi, fqn: &str) -> GcObjectBuilder<'abi> {
+        let ty = abi
+            .get_type_by_fqn(fqn)
+            .ok_or_else(|| Error::UnknownTypeName {
+                fqn: fqn.to_string(),
+            });
+        let nb_attrs = match &ty {
+            Ok(ty) => ty.attrs.as_deref().map_or(0, <[_]>::len),
+            Err(_) => 0,
+        };
+        GcObjectBuilder {
+            abi,
+            ty,
+            values: vec![None; nb_attrs],
+            error: None,
+        }
+    }
+}
+
+/// Builds a [`GcObject`] from its attributes given by name, in any order.
+///
+/// Errors are reported by [`build`](Self::build): an unknown type or attribute name, or a
+/// non-nullable attribute that is not set. Nullable attributes that are not set are `null`.
+///
+/// ```ignore
+/// let bob = GcObject::builder(&abi, "project::Person")
+///     .set("age", Value::Int(42))
+///     .set("name", Value::String("Bob".into()))
+///     .build()?;
+/// ```
+pub struct GcObjectBuilder<'abi> {
+    abi: &'abi Abi,
+    ty: Result<Arc<AbiType>>,
+    values: Vec<Option<Value<'abi>>>,
+    /// the first unknown attribute
+    error: Option<Error>,
+}
+
+impl<'abi> GcObjectBuilder<'abi> {
+    /// Sets the value of the attribute `name`, replacing the previous one
+    pub fn set(mut self, name: &str, value: Value<'abi>) -> Self {
+        let Ok(ty) = &self.ty else {
+            return self;
+        };
+        match ty.attr_offset(name, self.abi) {
+            Some(offset) => self.values[offset] = Some(value),
+            None => {
+                self.error.get_or_insert_with(|| Error::UnknownAttr {
+                    ty: ty.named_fqn(self.abi),
+                    attr: name.to_string(),
+                });
+            }
+        }
+        self
+    }
+
+    /// Returns the object, with its values in the order of the attributes of its type
+    pub fn build(self) -> Result<GcObject<'abi>> {
+        let ty = self.ty?;
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        let attrs = ty.attrs.as_deref().unwrap_or_default();
+        let missing: Vec<String> = attrs
+            .iter()
+            .zip(&self.values)
+            .filter(|(attr, value)| !attr.nullable && value.is_none())
+            .map(|(attr, _)| self.abi.symbols[attr.name].to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::MissingAttrs {
+                ty: ty.named_fqn(self.abi),
+                attrs: missing,
+            });
         }
+
+        let values: Vec<Value<'abi>> = self
+            .values
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+        Ok(GcObject::new(ty, Some(values)))
     }
 }
 
@@ -55,12 +186,23 @@ impl<'abi> GcObject<'abi> {
 // where
 //     R: std::io::Read,
 // {
-//     fn load(&mut self, ty: Rc<AbiType>, abi: &Abi) -> Result<Value> {
+//     fn load(&mut self, ty: Arc<AbiType>, abi: &Abi) -> Result<Value> {
 //         let value = AbiDeserialize::read_typed_object(self, ty, abi)?;
 //         Ok(Value::Obj(value))
 //     }
 // }
 
+impl GcObject<'_> {
+    fn mismatch(&self, attr: &AbiAttr, expected: &'static str, got: &Value, abi: &Abi) -> Error {
+        Error::AttrTypeMismatch {
+            ty: self.ty.named_fqn(abi),
+            attr: abi.symbols[attr.name].to_string(),
+            expected,
+            got: got.type_name(abi),
+        }
+    }
+}
+
 impl AbiSerialize for GcObject<'_> {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         writer.write_u8(primitive::OBJECT)?;
@@ -70,25 +212,35 @@ impl AbiSerialize for GcObject<'_> {
     }
 
     fn write_raw_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
+        if self.ty.is_native {
+            // only the libraries know the layouts of their native types
+            return match abi.get_loader(&self.ty) {
+                Some(loader) => loader.save(self, writer, abi),
+                None => Err(Error::Unsupported {
+                    ty: self.ty.named_fqn(abi),
+                    offset: None,
+                }),
+            };
+        }
         match (self.ty.attrs.as_ref(), self.values.as_ref()) {
             (None, None) => Ok(0),
-            (None, Some(values)) => anyhow::bail!(
-                "object '{}' has 0 attribute defined but {} values",
-                self.ty.name,
-                values.borrow().len()
-            ),
-            (Some(attrs), None) => anyhow::bail!(
-                "object '{}' has {} attributes defined but 0 value",
-                self.ty.name,
-                attrs.len()
-            ),
+            (None, Some(values)) => Err(Error::AttrCountMismatch {
+                ty: self.ty.named_fqn(abi),
+                expected: 0,
+                got: values.len(),
+            }),
+            (Some(attrs), None) => Err(Error::AttrCountMismatch {
+                ty: self.ty.named_fqn(abi),
+                expected: attrs.len(),
+                got: 0,
+            }),
             (Some(attrs), Some(values)) => {
                 let mut n = 0;
 
                 if self.ty.nullable_nb_bytes > 0 {
                     let mut nullable_bitset = vec![0u8; self.ty.nullable_nb_bytes as usize];
                     let mut nullable_offset = 0;
-                    for (att, value) in attrs.iter().zip(values.borrow().iter()) {
+                    for (att, value) in attrs.iter().zip(values.iter()) {
                         if att.nullable {
                             match value {
                                 Value::Null => {
@@ -103,7 +255,7 @@ impl AbiSerialize for GcObject<'_> {
                     n += self.ty.nullable_nb_bytes as usize;
                 }
 
-                for (attr, value) in attrs.iter().zip(values.borrow().iter()) {
+                for (attr, value) in attrs.iter().zip(values.iter()) {
                     if attr.nullable && matches!(value, Value::Null) {
                         // skip nullable attr that is actually 'null'
                         continue;
@@ -114,49 +266,25 @@ impl AbiSerialize for GcObject<'_> {
                             Value::Bool(v) => {
                                 n += v.write_raw_to(writer, abi)?;
                             }
-                            v => {
-                                anyhow::bail!(
-                                    "expected attribute '{}' in '{}' to be a bool, got {v}",
-                                    attr.name,
-                                    self.ty.name,
-                                )
-                            }
+                            v => return Err(self.mismatch(attr, "bool", v, abi)),
                         },
                         primitive::CHAR => match value {
                             Value::Char(v) => {
                                 n += v.write_raw_to(writer, abi)?;
                             }
-                            v => {
-                                anyhow::bail!(
-                                    "expected attribute '{}' in '{}' to be a char, got {v}",
-                                    attr.name,
-                                    self.ty.name,
-                                )
-                            }
+                            v => return Err(self.mismatch(attr, "char", v, abi)),
                         },
                         primitive::INT => match value {
                             Value::Int(v) => {
                                 n += v.write_raw_to(writer, abi)?;
                             }
-                            v => {
-                                anyhow::bail!(
-                                    "expected attribute '{}' in '{}' to be an int, got {v}",
-                                    attr.name,
-                                    self.ty.name,
-                                )
-                            }
+                            v => return Err(self.mismatch(attr, "int", v, abi)),
                         },
                         primitive::FLOAT => match value {
                             Value::Float(v) => {
                                 n += v.write_raw_to(writer, abi)?;
                             }
-                            v => {
-                                anyhow::bail!(
-                                    "expected attribute '{}' in '{}' to be a float, got {v}",
-                                    attr.name,
-                                    self.ty.name,
-                                )
-                            }
+                            v => return Err(self.mismatch(attr, "float", v, abi)),
                         },
                         primitive::OBJECT => match value {
                             Value::Obj(v) => {
@@ -177,13 +305,7 @@ impl AbiSerialize for GcObject<'_> {
                             Value::Enum(v) => {
                                 n += v.write_raw_to(writer, abi)?;
                             }
-                            v => {
-                                anyhow::bail!(
-                                    "expected attribute '{}' in '{}' to be an object, got {v}",
-                                    attr.name,
-                                    self.ty.name,
-                                )
-                            }
+                            v => return Err(self.mismatch(attr, "object", v, abi)),
                         },
                         primitive::UNDEFINED => {
                             n += value.write_to(writer, abi)?;
@@ -201,16 +323,14 @@ impl AbiSerialize for GcObject<'_> {
     }
 }
 
+/// The names of the type and of its attributes are only known with the ABI, use
+/// [`Value::display`] to print them
 impl std::fmt::Debug for GcObject<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut s = f.debug_struct(&self.ty.fqn());
-        if let (Some(attrs), Some(values)) = (self.ty.attrs.as_ref(), self.values.as_ref()) {
-            for (attr, value) in attrs.iter().zip(values.borrow().iter()) {
-                // TODO do better for GcObject debug
-                s.field(&format!("#{}", attr.name), value);
-            }
-        }
-        s.finish()
+        f.debug_struct("GcObject")
+            .field("ty", &self.ty.offset)
+            .field("values", &self.values)
+            .finish()
     }
 }
 
@@ -218,69 +338,13 @@ impl std::hash::Hash for GcObject<'_> {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.ty.mapped_abi_type_offset.hash(state);
         if let Some(values) = self.values.as_ref() {
-            for v in values.borrow().as_ref() {
+            for v in values.iter() {
                 v.hash(state);
             }
         }
     }
 }
 
-impl serde::Serialize for GcObject<'_> {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
-        use serde::ser::SerializeMap;
-
-        match self.ty.attrs.as_ref() {
-            Some(attrs) => {
-                let mut s = serializer.serialize_map(Some(attrs.len()))?;
-                // s.serialize_entry("_type", &self.ty.fqn())?;
-                for (attr, value) in attrs.iter().zip(self.values.iter()) {
-                    // TODO do better for GcObject debug
-                    s.serialize_entry(&format!("#{}", attr.name), value)?;
-                }
-                s.end()
-            }
-            None => serializer.serialize_map(None)?.end(),
-        }
-    }
-}
-
-pub struct RefValue<'r, 'abi> {
-    inner: Ref<'r, Box<[Value<'abi>]>>,
-    index: usize,
-}
-
-impl<'r, 'abi> RefValue<'r, 'abi> {
-    pub fn get(&self) -> &Value<'abi> {
-        &self.inner[self.index]
-    }
-}
-
-impl<'r, 'abi> Eq for RefValue<'r, 'abi> {}
-
-impl<'r, 'abi> PartialEq for RefValue<'r, 'abi> {
-    fn eq(&self, other: &Self) -> bool {
-        self.get() == other.get()
-    }
-}
-
-impl<'r, 'abi> std::fmt::Debug for RefValue<'r, 'abi> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.get().fmt(f)
-    }
-}
-
-impl<'r, 'abi> std::ops::Deref for RefValue<'r, 'abi> {
-    type Target = Value<'abi>;
-
-    #[inline(always)]
-    fn deref(&self) -> &Self::Target {
-        self.get()
-    }
-}
-
 const GC_OBJECT_BITSET_BLOCK_SIZE: usize = 8;
 
 #[inline]
@@ -309,3 +373,78 @@ pub(crate) fn gc_object_set_not_null(bitset: &mut [u8], offset: usize) {
     // Set the bit at the specified position to 1 using bitwise OR with 1 at that position
     bitset[bitset_index] |= 1 << bit_position;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::{project_abi, rebuild_abi};
+
+    #[test]
+    fn builder_by_name() {
+        let abi = project_abi();
+        // makes `name` nullable
+        let abi = rebuild_abi(&abi, &[], |name, attrs| {
+            if name == "Person" {
+                attrs[0].nullable = true;
+            }
+        });
+
+        let bob = GcObject::builder(&abi, "project::Person")
+            .set("age", Value::Int(42))
+            .build()
+            .unwrap();
+        assert_eq!(
+            bob.values.as_deref().unwrap(),
+            [Value::Null, Value::Int(42)]
+        );
+
+        let err = GcObject::builder(&abi, "project::Person")
+            .set("name", Value::String("Bob".into()))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "missing attributes for 'project::Person': age"
+        );
+
+        let err = GcObject::builder(&abi, "project::Person")
+            .set("age", Value::Int(42))
+            .set("email", Value::Null)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::UnknownAttr { attr, .. } if attr == "email"));
+    }
+
+    #[test]
+    fn attrs_by_name() {
+        let abi = project_abi();
+        let mut bob = GcObject::builder(&abi, "project::Person")
+            .set("name", Value::String("Bob".into()))
+            .set("age", Value::Int(42))
+            .build()
+            .unwrap();
+
+        assert_eq!(bob.get("age", &abi).unwrap(), &Value::Int(42));
+        bob.set("age", Value::Int(43), &abi).unwrap();
+        let named: Vec<_> = bob.iter_named(&abi).unwrap().collect();
+        assert_eq!(
+            named,
+            [
+                ("name", &Value::String("Bob".into())),
+                ("age", &Value::Int(43))
+            ]
+        );
+
+        assert!(matches!(
+            bob.get("email", &abi),
+            Err(Error::UnknownAttr { .. })
+        ));
+        let mut empty = GcObject::new(bob.ty.clone(), None::<Vec<_>>);
+        assert!(matches!(
+            empty.set("age", Value::Int(1), &abi),
+            Err(Error::AttrCountMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,1182 @@
+//! [`serde`] support for the GreyCat binary format.
+//!
+//! [`to_writer`] writes any [`Serialize`] type as a value of an [`AbiType`] and [`from_reader`]
+//! reads a value back into any [`DeserializeOwned`] type, both directly over the stream:
+//!
+//! - structs are objects, their fields are the attributes of the same name
+//! - unit variants are the variants of the same name of a GreyCat enum
+//! - sequences are `core::Array` and maps `core::Map`
+//! - `Option::None` and `()` are `null`
+//! - integers are `time`, `duration`, `float` or nodes when the attribute is of that type
+//!
+//! The type of the attributes drives the conversion. Where the ABI does not tell the expected
+//! type, eg. for the elements of an `Array`, a struct must be named after the fqn of its type
+//! with `#[serde(rename = "project::Person")]`, and an enum after the fqn of its GreyCat enum.
+//!
+//! The attributes of an object are buffered until the object ends, as the nullable bitset comes
+//! first and the fields of a struct may not be in the order of the attributes. So are the
+//! elements of a sequence of unknown length.
+//!
+//! Enum variants holding data have no GreyCat counterpart and are rejected.
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use byteorder::WriteBytesExt;
+use serde::de::value::{MapDeserializer, StrDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::abi::{Abi, AbiType};
+use crate::deserialize::{ty, AbiDeserialize};
+use crate::error::{Error, Result, Tracked};
+use crate::gc_object::{attr_is_null, gc_object_set_not_null};
+use crate::primitive;
+use crate::serialize::AbiSerialize;
+use crate::std_n::core::{
+    Duration, GcString, Geo, Node, NodeGeo, NodeIndex, NodeList, NodeTime, Time,
+};
+use crate::varint::{VarintRead, VarintWrite};
+
+/// Writes `value`, with its header, as a value of type `ty`
+pub fn to_writer<W, T>(writer: &mut W, value: &T, abi: &Abi, ty: &AbiType) -> Result<usize>
+where
+    W: Write,
+    T: Serialize + ?Sized,
+{
+    if !ty.is_type_of(abi) {
+        return Err(Error::UnknownType {
+            id: ty.offset,
+            offset: None,
+        });
+    }
+    value.serialize(Serializer {
+        writer,
+        abi,
+        ty: abi.types.get(ty.offset),
+        sbi_type: primitive::UNDEFINED,
+        null: None,
+    })
+}
+
+/// Reads a value, with its header, as a `T`
+pub fn from_reader<R, T>(reader: R, abi: &Abi) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    Tracked::run(reader, |reader| {
+        T::deserialize(Deserializer {
+            reader,
+            abi,
+            next: Next::Value,
+        })
+    })
+}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string().into())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::Custom(msg.to_string().into())
+    }
+}
+
+/// Returns the name of `ty` if it is a core type
+fn core_name<'a>(ty: Option<&AbiType>, abi: &'a Abi) -> Option<&'a str> {
+    let ty = ty?;
+    (&abi.symbols[ty.module] == "core").then(|| &abi.symbols[ty.name])
+}
+
+/// Returns the type named `name` by a Rust struct or enum, which must be its fqn
+fn named_type(name: &str, abi: &Abi) -> Result<Arc<AbiType>> {
+    abi.get_type_by_fqn(name)
+        .ok_or_else(|| Error::UnknownTypeName {
+            fqn: name.to_string(),
+        })
+}
+
+fn is_object(ty: &AbiType, abi: &Abi) -> bool {
+    core_name(Some(ty), abi).is_none() && !ty.is_enum && !ty.is_native && !ty.is_abstract
+}
+
+fn data_variant(name: &str, variant: &str) -> Error {
+    Error::ValueMismatch {
+        expected: "a unit variant".to_string(),
+        got: format!("{name}::{variant}"),
+    }
+}
+
+struct Serializer<'a, W> {
+    writer: &'a mut W,
+    abi: &'a Abi,
+    /// the expected type, if known
+    ty: Option<Arc<AbiType>>,
+    /// the binary type of the attribute being written, the value has a header if `UNDEFINED`
+    sbi_type: u8,
+    /// set instead of writing `null` for a nullable attribute, which is `null` in the bitset
+    null: Option<&'a mut bool>,
+}
+
+impl<'a, W: Write> Serializer<'a, W> {
+    fn new(writer: &'a mut W, abi: &'a Abi) -> Self {
+        Self {
+            writer,
+            abi,
+            ty: None,
+            sbi_type: primitive::UNDEFINED,
+            null: None,
+        }
+    }
+
+    fn mismatch(&self, got: &str) -> Error {
+        Error::ValueMismatch {
+            expected: match &self.ty {
+                Some(ty) => ty.named_fqn(self.abi),
+                None => "any".to_string(),
+            },
+            got: got.to_string(),
+        }
+    }
+
+    /// Returns the primitive type numbers are written as, `UNDEFINED` when not constrained
+    fn target(&self) -> u8 {
+        if self.sbi_type != primitive::UNDEFINED {
+            return self.sbi_type;
+        }
+        match core_name(self.ty.as_deref(), self.abi) {
+            Some("float") => primitive::FLOAT,
+            Some("time") => primitive::TIME,
+            Some("duration") => primitive::DURATION,
+            Some("node") => primitive::NODE,
+            Some("nodeTime") => primitive::NODE_TIME,
+            Some("nodeIndex") => primitive::NODE_INDEX,
+            Some("nodeList") => primitive::NODE_LIST,
+            Some("nodeGeo") => primitive::NODE_GEO,
+            Some("geo") => primitive::GEO,
+            _ => primitive::UNDEFINED,
+        }
+    }
+
+    /// Writes `value` with its header, or without if written as an attribute of type `primitive`
+    fn write<T: AbiSerialize>(self, value: &T, primitive: u8, got: &str) -> Result<usize> {
+        match self.sbi_type {
+            primitive::UNDEFINED => value.write_to(self.writer, self.abi),
+            sbi if sbi == primitive => value.write_raw_to(self.writer, self.abi),
+            _ => Err(self.mismatch(got)),
+        }
+    }
+
+    /// Writes the header of an object of type `id`, which is only its type for an attribute of
+    /// an abstract type and nothing for an attribute of that type
+    fn object_header(&mut self, id: u32, got: &str) -> Result<usize> {
+        match (self.sbi_type, &self.ty) {
+            (primitive::UNDEFINED, _) => {
+                self.writer.write_u8(primitive::OBJECT)?;
+                Ok(1 + self.writer.write_vu32(id)?)
+            }
+            (primitive::OBJECT, Some(ty)) if ty.is_abstract => Ok(self.writer.write_vu32(id)?),
+            (primitive::OBJECT, Some(ty)) if ty.mapped_abi_type_offset == id => Ok(0),
+            _ => Err(self.mismatch(got)),
+        }
+    }
+
+    fn signed(self, v: i64) -> Result<usize> {
+        match self.target() {
+            primitive::UNDEFINED | primitive::INT => self.write(&v, primitive::INT, "int"),
+            primitive::FLOAT => self.write(&(v as f64), primitive::FLOAT, "int"),
+            primitive::TIME => self.write(&Time(v), primitive::TIME, "int"),
+            primitive::DURATION => self.write(&Duration(v), primitive::DURATION, "int"),
+            primitive::NODE..=primitive::GEO => {
+                let v = u64::try_from(v).map_err(|_| Error::ValueMismatch {
+                    expected: "a positive integer".to_string(),
+                    got: v.to_string(),
+                })?;
+                self.unsigned(v)
+            }
+            _ => Err(self.mismatch("int")),
+        }
+    }
+
+    fn unsigned(self, v: u64) -> Result<usize> {
+        match self.target() {
+            primitive::NODE => self.write(&Node(v), primitive::NODE, "int"),
+            primitive::NODE_TIME => self.write(&NodeTime(v), primitive::NODE_TIME, "int"),
+            primitive::NODE_INDEX => self.write(&NodeIndex(v), primitive::NODE_INDEX, "int"),
+            primitive::NODE_LIST => self.write(&NodeList(v), primitive::NODE_LIST, "int"),
+            primitive::NODE_GEO => self.write(&NodeGeo(v), primitive::NODE_GEO, "int"),
+            primitive::GEO => self.write(&Geo(v), primitive::GEO, "int"),
+            _ => {
+                let v = i64::try_from(v).map_err(|_| Error::ValueMismatch {
+                    expected: "int".to_string(),
+                    got: v.to_string(),
+                })?;
+                self.signed(v)
+            }
+        }
+    }
+
+    fn variant(self, en: &AbiType, variant: &str) -> Result<usize> {
+        let value = self.abi.enum_value(&en.named_fqn(self.abi), variant)?;
+        self.write(&value, primitive::ENUM, variant)
+    }
+
+    fn null(mut self) -> Result<usize> {
+        if let Some(null) = self.null.as_deref_mut() {
+            *null = true;
+            return Ok(0);
+        }
+        match self.sbi_type {
+            primitive::UNDEFINED => {
+                self.writer.write_u8(primitive::NULL)?;
+                Ok(1)
+            }
+            _ => Err(self.mismatch("null")),
+        }
+    }
+
+    /// Writes the header of a sequence of `core` type `id`, then its length if known
+    fn seq(mut self, id: u32, len: Option<usize>, got: &str) -> Result<SeqSerializer<'a, W>> {
+        let mut n = self.object_header(id, got)?;
+        let buf = match len {
+            Some(len) => {
+                n += self.writer.write_vu32(len as u32)?;
+                None
+            }
+            None => Some(Vec::new()),
+        };
+        Ok(SeqSerializer {
+            writer: self.writer,
+            abi: self.abi,
+            n,
+            len: 0,
+            expected: len,
+            buf,
+        })
+    }
+}
+
+impl<'a, W: Write> ser::Serializer for Serializer<'a, W> {
+    type Ok = usize;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = SeqSerializer<'a, W>;
+    type SerializeTupleStruct = SeqSerializer<'a, W>;
+    type SerializeTupleVariant = Impossible<usize, Error>;
+    type SerializeMap = SeqSerializer<'a, W>;
+    type SerializeStruct = StructSerializer<'a, W>;
+    type SerializeStructVariant = Impossible<usize, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<usize> {
+        self.write(&v, primitive::BOOL, "bool")
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<usize> {
+        self.signed(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<usize> {
+        self.signed(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<usize> {
+        self.signed(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<usize> {
+        self.signed(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<usize> {
+        self.unsigned(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<usize> {
+        self.unsigned(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<usize> {
+        self.unsigned(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<usize> {
+        self.unsigned(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<usize> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<usize> {
+        match self.target() {
+            primitive::UNDEFINED | primitive::FLOAT => self.write(&v, primitive::FLOAT, "float"),
+            _ => Err(self.mismatch("float")),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<usize> {
+        self.write(&v, primitive::CHAR, "char")
+    }
+
+    fn serialize_str(mut self, v: &str) -> Result<usize> {
+        if let Some(en) = self.ty.clone().filter(|ty| ty.is_enum) {
+            return self.variant(&en, v);
+        }
+        if self.sbi_type == primitive::UNDEFINED {
+            return v.write_to(self.writer, self.abi);
+        }
+        let n = self.object_header(self.abi.types.core.string, "String")?;
+        Ok(n + v.write_raw_to(self.writer, self.abi)?)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<usize> {
+        let mut seq = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+        for b in v {
+            ser::SerializeSeq::serialize_element(&mut seq, b)?;
+        }
+        ser::SerializeSeq::end(seq)
+    }
+
+    fn serialize_none(self) -> Result<usize> {
+        self.null()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<usize>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<usize> {
+        self.null()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<usize> {
+        self.null()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<usize> {
+        let en = match self.ty.clone().filter(|ty| ty.is_enum) {
+            Some(ty) => ty,
+            None => named_type(name, self.abi)?,
+        };
+        self.variant(&en, variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<usize>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &T,
+    ) -> Result<usize>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(data_variant(name, variant))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer<'a, W>> {
+        let id = self.abi.types.core.array;
+        self.seq(id, len, "Array")
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(data_variant(name, variant))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SeqSerializer<'a, W>> {
+        let id = self.abi.types.core.map;
+        self.seq(id, len, "Map")
+    }
+
+    fn serialize_struct(
+        mut self,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<StructSerializer<'a, W>> {
+        let abi = self.abi;
+        let expected = self.ty.clone().filter(|ty| !ty.is_abstract);
+        let geo = match &expected {
+            Some(ty) => core_name(Some(ty), abi) == Some("geo"),
+            None => name == Geo::TYPE,
+        };
+        if geo {
+            return Ok(StructSerializer::Geo {
+                ser: self,
+                lat: None,
+                lng: None,
+            });
+        }
+
+        let ty = match expected {
+            Some(ty) => ty,
+            None => named_type(name, abi)?,
+        };
+        if !is_object(&ty, abi) {
+            return Err(Error::ValueMismatch {
+                expected: ty.named_fqn(abi),
+                got: name.to_string(),
+            });
+        }
+        let n = self.object_header(ty.mapped_abi_type_offset, name)?;
+        let attrs = ty.attrs.as_deref().unwrap_or_default();
+        Ok(StructSerializer::Object {
+            writer: self.writer,
+            abi,
+            values: attrs.iter().map(|_| Attr::Unset).collect(),
+            ty,
+            n,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(data_variant(name, variant))
+    }
+}
+
+/// Writes the elements of a `core::Array`, or the entries of a `core::Map`
+struct SeqSerializer<'a, W> {
+    writer: &'a mut W,
+    abi: &'a Abi,
+    /// the number of bytes written so far
+    n: usize,
+    /// the number of elements, keys and values counting for one
+    len: usize,
+    /// the length that was written ahead, if known
+    expected: Option<usize>,
+    /// the elements when the length is not known ahead
+    buf: Option<Vec<u8>>,
+}
+
+impl<W: Write> SeqSerializer<'_, W> {
+    fn write<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        let abi = self.abi;
+        self.n += match &mut self.buf {
+            Some(buf) => value.serialize(Serializer::new(buf, abi))?,
+            None => value.serialize(Serializer::new(&mut *self.writer, abi))?,
+        };
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<usize> {
+        match (self.expected, self.buf) {
+            (Some(expected), _) if expected != self.len => Err(Error::ValueMismatch {
+                expected: format!("{expected} elements"),
+                got: self.len.to_string(),
+            }),
+            (_, Some(buf)) => {
+                self.n += self.writer.write_vu32(self.len as u32)?;
+                self.writer.write_all(&buf)?;
+                Ok(self.n)
+            }
+            _ => Ok(self.n),
+        }
+    }
+}
+
+impl<W: Write> ser::SerializeSeq for SeqSerializer<'_, W> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write(value)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<usize> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeTuple for SeqSerializer<'_, W> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<usize> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeTupleStruct for SeqSerializer<'_, W> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<usize> {
+        SeqSerializer::end(self)
+    }
+}
+
+impl<W: Write> ser::SerializeMap for SeqSerializer<'_, W> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.write(value)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end(self) -> Result<usize> {
+        SeqSerializer::end(self)
+    }
+}
+
+/// The bytes of an attribute
+enum Attr {
+    Unset,
+    Null,
+    Set(Vec<u8>),
+}
+
+enum StructSerializer<'a, W> {
+    /// an object, whose attributes are written once all set
+    Object {
+        writer: &'a mut W,
+        abi: &'a Abi,
+        ty: Arc<AbiType>,
+        /// the number of bytes of the header
+        n: usize,
+        values: Vec<Attr>,
+    },
+    /// a `geo` from its `lat` and `lng`
+    Geo {
+        ser: Serializer<'a, W>,
+        lat: Option<f64>,
+        lng: Option<f64>,
+    },
+}
+
+impl<W: Write> ser::SerializeStruct for StructSerializer<'_, W> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self {
+            StructSerializer::Object {
+                abi, ty, values, ..
+            } => {
+                let abi = *abi;
+                let offset = ty.attr_offset(key, abi).ok_or_else(|| Error::UnknownAttr {
+                    ty: ty.named_fqn(abi),
+                    attr: key.to_string(),
+                })?;
+                let attr = &ty.attrs.as_deref().unwrap_or_default()[offset];
+                let mut buf = Vec::new();
+                let mut null = false;
+                value.serialize(Serializer {
+                    writer: &mut buf,
+                    abi,
+                    ty: attr.prog_type(abi),
+                    sbi_type: attr.sbi_type,
+                    null: attr.nullable.then_some(&mut null),
+                })?;
+                values[offset] = if null { Attr::Null } else { Attr::Set(buf) };
+            }
+            StructSerializer::Geo { ser, lat, lng } => {
+                let coord = match key {
+                    "lat" => lat,
+                    "lng" => lng,
+                    _ => {
+                        return Err(Error::UnknownAttr {
+                            ty: Geo::TYPE.to_string(),
+                            attr: key.to_string(),
+                        })
+                    }
+                };
+                // a float attribute is its 8 little-endian bytes
+                let mut buf = Vec::new();
+                value.serialize(Serializer {
+                    writer: &mut buf,
+                    abi: ser.abi,
+                    ty: None,
+                    sbi_type: primitive::FLOAT,
+                    null: None,
+                })?;
+                *coord = buf.try_into().ok().map(f64::from_le_bytes);
+            }
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<usize> {
+        match self {
+            StructSerializer::Object {
+                writer,
+                abi,
+                ty,
+                mut n,
+                values,
+            } => {
+                let attrs = ty.attrs.as_deref().unwrap_or_default();
+                let missing: Vec<_> = attrs
+                    .iter()
+                    .zip(&values)
+                    .filter(|(attr, value)| !attr.nullable && matches!(value, Attr::Unset))
+                    .map(|(attr, _)| abi.symbols[attr.name].to_string())
+                    .collect();
+                if !missing.is_empty() {
+                    return Err(Error::MissingAttrs {
+                        ty: ty.named_fqn(abi),
+                        attrs: missing,
+                    });
+                }
+
+                if ty.nullable_nb_bytes > 0 {
+                    let mut nullable_bitset = vec![0u8; ty.nullable_nb_bytes as usize];
+                    let nullables = attrs.iter().zip(&values).filter(|(attr, _)| attr.nullable);
+                    for (offset, (_, value)) in nullables.enumerate() {
+                        if let Attr::Set(_) = value {
+                            gc_object_set_not_null(&mut nullable_bitset, offset);
+                        }
+                    }
+                    writer.write_all(&nullable_bitset)?;
+                    n += nullable_bitset.len();
+                }
+                for value in &values {
+                    if let Attr::Set(bytes) = value {
+                        writer.write_all(bytes)?;
+                        n += bytes.len();
+                    }
+                }
+                Ok(n)
+            }
+            StructSerializer::Geo { ser, lat, lng } => match (lat, lng) {
+                (Some(lat), Some(lng)) => {
+                    ser.write(&Geo::from_lat_lng(lat, lng), primitive::GEO, Geo::TYPE)
+                }
+                _ => Err(Error::MissingAttrs {
+                    ty: Geo::TYPE.to_string(),
+                    attrs: vec!["lat".to_string(), "lng".to_string()],
+                }),
+            },
+        }
+    }
+}
+
+/// What is read next
+#[derive(Clone)]
+enum Next {
+    /// a value with its header
+    Value,
+    /// a `null` attribute, which has no bytes
+    Null,
+    /// a value of that primitive type, without header
+    Primitive(u8),
+    /// a variant of that enum, without header
+    Enum(Arc<AbiType>),
+    /// an object of an abstract type, preceded by its concrete type
+    Abstract,
+    /// an object of that type, without header
+    Object(Arc<AbiType>),
+}
+
+struct Deserializer<'a, R> {
+    reader: &'a mut R,
+    abi: &'a Abi,
+    next: Next,
+}
+
+impl<R: Read> Deserializer<'_, R> {
+    /// Reads the header of the value, and then the type of an enum or object, if any
+    fn resolve(&mut self) -> Result<Next> {
+        let abi = self.abi;
+        let next = match std::mem::replace(&mut self.next, Next::Value) {
+            Next::Value => Next::Primitive(byteorder::ReadBytesExt::read_u8(self.reader)?),
+            next => next,
+        };
+        let next = match next {
+            Next::Primitive(primitive::ENUM) => Next::Enum(ty(abi, self.reader.read_vu32()?)?),
+            Next::Primitive(primitive::OBJECT) | Next::Abstract => {
+                Next::Object(ty(abi, self.reader.read_vu32()?)?)
+            }
+            next => next,
+        };
+        Ok(next)
+    }
+
+    /// Visits `len` values of the stream as a sequence
+    fn seq<'de, V>(self, len: u32, next: Next, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seq = SeqAccess {
+            reader: self.reader,
+            abi: self.abi,
+            len,
+            next,
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        match seq.len {
+            0 => Ok(value),
+            len => Err(de::Error::invalid_length(len as usize, &"fewer elements")),
+        }
+    }
+
+    fn object<'de, V>(self, ty: Arc<AbiType>, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let abi = self.abi;
+        let unsupported = || Error::Unsupported {
+            ty: ty.named_fqn(abi),
+            offset: None,
+        };
+        if ty.is_native {
+            // the types of libraries are loaded as `Value`s, which `serde` does not visit
+            if abi.get_loader(&ty).is_some() {
+                return Err(unsupported());
+            }
+            let core = &abi.types.core;
+            let reader = self.reader;
+            return match ty.mapped_abi_type_offset {
+                id if id == core.string => match reader.read_string(abi)? {
+                    GcString::Symbol(symbol) => visitor.visit_str(symbol.0),
+                    GcString::String(v) => visitor.visit_string(v),
+                },
+                id if id == core.array => {
+                    let len = reader.read_vu32()?;
+                    Deserializer::new(reader, abi).seq(len, Next::Value, visitor)
+                }
+                id if id == core.map => {
+                    let len = reader.read_vu32()?;
+                    let mut map = EntriesAccess { reader, abi, len };
+                    let value = visitor.visit_map(&mut map)?;
+                    match map.len {
+                        0 => Ok(value),
+                        len => Err(de::Error::invalid_length(len as usize, &"fewer entries")),
+                    }
+                }
+                // the layouts of the other core native types are not known
+                _ => Err(unsupported()),
+            };
+        }
+
+        // the attributes of `ty` are read and named, the object being of its mapped type
+        let nullable_nb_bytes = match ty.attrs {
+            Some(_) => ty.nullable_nb_bytes as usize,
+            None => 0,
+        };
+        let mut nullable_bitset = vec![0u8; nullable_nb_bytes];
+        self.reader.read_exact(&mut nullable_bitset)?;
+        let mut object = ObjectAccess {
+            reader: self.reader,
+            abi,
+            ty,
+            nullable_bitset,
+            attr: 0,
+            nullable: 0,
+            next: Next::Value,
+        };
+        let value = visitor.visit_map(&mut object)?;
+        let len = object.ty.attrs.as_deref().map_or(0, <[_]>::len);
+        match len - object.attr {
+            0 => Ok(value),
+            n => Err(de::Error::invalid_length(n, &"fewer attributes")),
+        }
+    }
+}
+
+impl<'a, R: Read> Deserializer<'a, R> {
+    fn new(reader: &'a mut R, abi: &'a Abi) -> Self {
+        Self {
+            reader,
+            abi,
+            next: Next::Value,
+        }
+    }
+}
+
+impl<'de, R: Read> de::Deserializer<'de> for Deserializer<'_, R> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let abi = self.abi;
+        let next = self.resolve()?;
+        let reader = &mut *self.reader;
+        match next {
+            Next::Null | Next::Primitive(primitive::NULL) => visitor.visit_unit(),
+            Next::Primitive(primitive::INT) => visitor.visit_i64(reader.read_int()?),
+            Next::Primitive(primitive::FLOAT) => visitor.visit_f64(reader.read_float()?),
+            Next::Primitive(primitive::BOOL) => visitor.visit_bool(reader.read_bool()?),
+            Next::Primitive(primitive::CHAR) => visitor.visit_char(reader.read_char()?),
+            Next::Primitive(primitive::NODE) => visitor.visit_u64(reader.read_node()?.0),
+            Next::Primitive(primitive::NODE_TIME) => visitor.visit_u64(reader.read_nodetime()?.0),
+            Next::Primitive(primitive::NODE_INDEX) => visitor.visit_u64(reader.read_nodeindex()?.0),
+            Next::Primitive(primitive::NODE_LIST) => visitor.visit_u64(reader.read_nodelist()?.0),
+            Next::Primitive(primitive::NODE_GEO) => visitor.visit_u64(reader.read_nodegeo()?.0),
+            Next::Primitive(primitive::GEO) => {
+                let (lat, lng) = reader.read_geo()?.as_lat_lng();
+                visitor.visit_map(MapDeserializer::new(
+                    [("lat", lat), ("lng", lng)].into_iter(),
+                ))
+            }
+            Next::Primitive(primitive::TIME) => visitor.visit_i64(reader.read_time()?.0),
+            Next::Primitive(primitive::DURATION) => visitor.visit_i64(reader.read_duration()?.0),
+            Next::Primitive(primitive::FN) => {
+                visitor.visit_string(reader.read_fn(abi)?.function.named_fqn(abi))
+            }
+            Next::Primitive(primitive::STR_LIT) => visitor.visit_str(reader.read_symbol(abi)?.0),
+            Next::Primitive(header) => Err(Error::UnknownPrimitive {
+                header,
+                offset: None,
+            }),
+            Next::Enum(ty) => visitor.visit_str(reader.read_typed_enum(ty, abi)?.key),
+            Next::Object(ty) => self.object(ty, visitor),
+            Next::Value | Next::Abstract => unreachable!("resolved above"),
+        }
+    }
+
+    fn deserialize_option<V>(mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.resolve()? {
+            Next::Null | Next::Primitive(primitive::NULL) => visitor.visit_none(),
+            next => visitor.visit_some(Deserializer { next, ..self }),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        mut self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let abi = self.abi;
+        let key = match self.resolve()? {
+            Next::Enum(ty) => self.reader.read_typed_enum(ty, abi)?.key,
+            Next::Primitive(primitive::STR_LIT) => self.reader.read_symbol(abi)?.0,
+            Next::Object(ty) if ty.mapped_abi_type_offset == abi.types.core.string => {
+                match self.reader.read_string(abi)? {
+                    GcString::Symbol(symbol) => symbol.0,
+                    GcString::String(v) => return visitor.visit_enum(v.into_deserializer()),
+                }
+            }
+            next => return Deserializer { next, ..self }.deserialize_any(visitor),
+        };
+        visitor.visit_enum(StrDeserializer::new(key))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+struct SeqAccess<'a, R> {
+    reader: &'a mut R,
+    abi: &'a Abi,
+    /// the number of elements left
+    len: u32,
+    next: Next,
+}
+
+impl<'de, R: Read> de::SeqAccess<'de> for SeqAccess<'_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(Deserializer {
+            reader: &mut *self.reader,
+            abi: self.abi,
+            next: self.next.clone(),
+        })
+        .map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len as usize)
+    }
+}
+
+/// The entries of a `core::Map`
+struct EntriesAccess<'a, R> {
+    reader: &'a mut R,
+    abi: &'a Abi,
+    /// the number of entries left
+    len: u32,
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for EntriesAccess<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(Deserializer::new(&mut *self.reader, self.abi))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(Deserializer::new(&mut *self.reader, self.abi))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len as usize)
+    }
+}
+
+/// The attributes of an object, by name
+struct ObjectAccess<'a, R> {
+    reader: &'a mut R,
+    abi: &'a Abi,
+    ty: Arc<AbiType>,
+    nullable_bitset: Vec<u8>,
+    /// the offset of the next attribute
+    attr: usize,
+    /// the offset of the next nullable attribute in the bitset
+    nullable: usize,
+    /// how the value of the last attribute is read
+    next: Next,
+}
+
+impl<'de, R: Read> de::MapAccess<'de> for ObjectAccess<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        let abi = self.abi;
+        let Some(attr) = self
+            .ty
+            .attrs
+            .as_deref()
+            .and_then(|attrs| attrs.get(self.attr))
+        else {
+            return Ok(None);
+        };
+        self.attr += 1;
+        let null = attr.nullable && {
+            self.nullable += 1;
+            attr_is_null(&self.nullable_bitset, self.nullable - 1)
+        };
+        self.next = match attr.sbi_type {
+            _ if null => Next::Null,
+            primitive::UNDEFINED => Next::Value,
+            primitive::ENUM => Next::Enum(ty(abi, attr.abi_type)?),
+            primitive::OBJECT => match ty(abi, attr.abi_type)? {
+                ty if ty.is_abstract => Next::Abstract,
+                ty => Next::Object(ty),
+            },
+            sbi => Next::Primitive(sbi),
+        };
+        seed.deserialize(StrDeserializer::new(&abi.symbols[attr.name]))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(Deserializer {
+            reader: &mut *self.reader,
+            abi: self.abi,
+            next: std::mem::replace(&mut self.next, Next::Value),
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        let len = self.ty.attrs.as_deref().map_or(0, <[_]>::len);
+        Some(len - self.attr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::abi::test::{person_status_abi, project_abi};
+    use crate::gc_object::GcObject;
+    use crate::value::Value;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename = "project::Person")]
+    struct Person {
+        name: String,
+        age: i64,
+        status: Option<Status>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Status {
+        Active,
+        Inactive,
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let abi = person_status_abi();
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+        let array = abi.get_type_by_fqn("core::Array").unwrap();
+
+        let bob = Person {
+            name: "Bob".to_string(),
+            age: 42,
+            status: Some(Status::Active),
+        };
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &bob, &abi, &person).unwrap();
+
+        let expected = GcObject::builder(&abi, "project::Person")
+            .set("name", Value::String("Bob".into()))
+            .set("age", Value::Int(42))
+            .set(
+                "status",
+                Value::Enum(abi.enum_value("project::Status", "Active").unwrap()),
+            )
+            .build()
+            .unwrap();
+        assert_eq!((&buf[..]).read_value(&abi).unwrap(), Value::Obj(expected));
+        assert_eq!(from_reader::<_, Person>(&buf[..], &abi).unwrap(), bob);
+
+        // the elements of an `Array` are of the type their struct is named after
+        let people = vec![
+            bob,
+            Person {
+                name: "Alice".to_string(),
+                age: 33,
+                status: None,
+            },
+        ];
+        let mut buf = Vec::new();
+        to_writer(&mut buf, &people, &abi, &array).unwrap();
+        assert_eq!(
+            from_reader::<_, Vec<Person>>(&buf[..], &abi).unwrap(),
+            people
+        );
+
+        let map = abi.get_type_by_fqn("core::Map").unwrap();
+        let ages = std::collections::BTreeMap::from([("Alice", 33), ("Bob", 42)]);
+        let mut buf = Vec::new();
+        let n = to_writer(&mut buf, &ages, &abi, &map).unwrap();
+        assert_eq!(n, buf.len());
+        assert_eq!(
+            from_reader::<_, std::collections::BTreeMap<String, i64>>(&buf[..], &abi).unwrap(),
+            ages.into_iter().map(|(k, v)| (k.to_string(), v)).collect()
+        );
+
+        #[derive(Serialize)]
+        struct Nobody {
+            name: String,
+        }
+        let nobody = [Nobody {
+            name: "Bob".to_string(),
+        }];
+        assert!(matches!(
+            to_writer(&mut Vec::new(), &nobody, &abi, &array),
+            Err(Error::UnknownTypeName { fqn }) if fqn == "Nobody"
+        ));
+        // `person` is a type of `abi`, not of `project_abi()`
+        assert!(matches!(
+            to_writer(&mut Vec::new(), &people[0], &project_abi(), &person),
+            Err(Error::UnknownType { id: 4, .. })
+        ));
+    }
+}
@@ -0,0 +1,366 @@
+//! Conversions between Rust types and [`Value`]s of GreyCat types.
+//!
+//! This is what the code generated by [`codegen`](crate::codegen) and `#[derive(GcType)]` rely on.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::abi::Abi;
+use crate::conform::type_name;
+use crate::deserialize::AbiDeserialize;
+use crate::error::{Error, Result, Tracked};
+use crate::gc_object::GcObject;
+use crate::std_n::core;
+use crate::value::Value;
+
+/// A Rust type that converts both ways to a [`Value`] of the GreyCat type [`GcType::TYPE`]
+pub trait GcType<'abi>: Sized {
+    /// The GreyCat type, eg. `core::int` or `project::Person`
+    const TYPE: &'static str;
+
+    fn to_value(&self, abi: &'abi Abi) -> Result<Value<'abi>>;
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self>;
+
+    /// Reads a value, with its header, as a `Self`
+    fn from_reader<R: Read>(reader: R, abi: &'abi Abi) -> Result<Self> {
+        let value = Tracked::run(reader, |reader| reader.read_value(abi))?;
+        Self::from_value(&value, abi)
+    }
+}
+
+/// Implements [`GcType`] and [`AbiSerialize`](crate::serialize::AbiSerialize) for a struct or an
+/// enum bound to a GreyCat type, see [`greycat_macro::GcType`]
+pub use greycat_macro::GcType;
+
+macro_rules! impl_gc_type {
+    ($ty:ty, $fqn:expr, $variant:ident) => {
+        impl<'abi> GcType<'abi> for $ty {
+            const TYPE: &'static str = $fqn;
+
+            #[inline]
+            fn to_value(&self, _abi: &'abi Abi) -> Result<Value<'abi>> {
+                Ok(Value::$variant(self.clone()))
+            }
+
+            fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+                match value {
+                    Value::$variant(v) => Ok(v.clone()),
+                    v => Err(mismatch(Self::TYPE, v, abi)),
+                }
+            }
+        }
+    };
+}
+
+impl_gc_type!(i64, "core::int", Int);
+impl_gc_type!(bool, "core::bool", Bool);
+impl_gc_type!(char, "core::char", Char);
+impl_gc_type!(core::Time, core::Time::TYPE, Time);
+impl_gc_type!(core::Duration, core::Duration::TYPE, Duration);
+impl_gc_type!(core::Geo, core::Geo::TYPE, Geo);
+impl_gc_type!(core::Node, core::Node::TYPE, Node);
+impl_gc_type!(core::NodeTime, core::NodeTime::TYPE, NodeTime);
+impl_gc_type!(core::NodeIndex, core::NodeIndex::TYPE, NodeIndex);
+impl_gc_type!(core::NodeList, core::NodeList::TYPE, NodeList);
+impl_gc_type!(core::NodeGeo, core::NodeGeo::TYPE, NodeGeo);
+
+impl<'abi> GcType<'abi> for f64 {
+    const TYPE: &'static str = "core::float";
+
+    #[inline]
+    fn to_value(&self, _abi: &'abi Abi) -> Result<Value<'abi>> {
+        Ok(Value::Float((*self).into()))
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::Float(v) => Ok(**v),
+            v => Err(mismatch(Self::TYPE, v, abi)),
+        }
+    }
+}
+
+impl<'abi> GcType<'abi> for String {
+    const TYPE: &'static str = "core::String";
+
+    #[inline]
+    fn to_value(&self, _abi: &'abi Abi) -> Result<Value<'abi>> {
+        Ok(Value::String(self.clone()))
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::String(v) => Ok(v.clone()),
+            Value::Symbol(v) => Ok(v.0.to_string()),
+            v => Err(mismatch(Self::TYPE, v, abi)),
+        }
+    }
+}
+
+/// `null`, eg. the return type of functions that return nothing
+impl<'abi> GcType<'abi> for () {
+    const TYPE: &'static str = "core::null";
+
+    #[inline]
+    fn to_value(&self, _abi: &'abi Abi) -> Result<Value<'abi>> {
+        Ok(Value::Null)
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::Null => Ok(()),
+            v => Err(mismatch(Self::TYPE, v, abi)),
+        }
+    }
+}
+
+/// Any value, as is
+impl<'abi> GcType<'abi> for Value<'abi> {
+    const TYPE: &'static str = "core::any";
+
+    #[inline]
+    fn to_value(&self, _abi: &'abi Abi) -> Result<Value<'abi>> {
+        Ok(self.clone())
+    }
+
+    #[inline]
+    fn from_value(value: &Value<'abi>, _abi: &'abi Abi) -> Result<Self> {
+        Ok(value.clone())
+    }
+}
+
+/// A nullable `T`
+impl<'abi, T: GcType<'abi>> GcType<'abi> for Option<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn to_value(&self, abi: &'abi Abi) -> Result<Value<'abi>> {
+        match self {
+            Some(v) => v.to_value(abi),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            v => T::from_value(v, abi).map(Some),
+        }
+    }
+}
+
+impl<'abi, T: GcType<'abi>> GcType<'abi> for Vec<T> {
+    const TYPE: &'static str = "core::Array";
+
+    fn to_value(&self, abi: &'abi Abi) -> Result<Value<'abi>> {
+        let values = self
+            .iter()
+            .map(|v| v.to_value(abi))
+            .collect::<Result<_>>()?;
+        Ok(Value::Array(values))
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::Array(values) => values.iter().map(|v| T::from_value(v, abi)).collect(),
+            v => Err(mismatch(Self::TYPE, v, abi)),
+        }
+    }
+}
+
+impl<'abi, K, V> GcType<'abi> for BTreeMap<K, V>
+where
+    K: GcType<'abi> + Ord,
+    V: GcType<'abi>,
+{
+    const TYPE: &'static str = "core::Map";
+
+    fn to_value(&self, abi: &'abi Abi) -> Result<Value<'abi>> {
+        let entries = self
+            .iter()
+            .map(|(k, v)| Ok((k.to_value(abi)?, v.to_value(abi)?)))
+            .collect::<Result<_>>()?;
+        Ok(Value::Map(entries))
+    }
+
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        match value {
+            Value::Map(entries) => entries
+                .iter()
+                .map(|(k, v)| Ok((K::from_value(k, abi)?, V::from_value(v, abi)?)))
+                .collect(),
+            v => Err(mismatch(Self::TYPE, v, abi)),
+        }
+    }
+}
+
+/// A field of a recursive type
+impl<'abi, T: GcType<'abi>> GcType<'abi> for Box<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    #[inline]
+    fn to_value(&self, abi: &'abi Abi) -> Result<Value<'abi>> {
+        (**self).to_value(abi)
+    }
+
+    #[inline]
+    fn from_value(value: &Value<'abi>, abi: &'abi Abi) -> Result<Self> {
+        T::from_value(value, abi).map(Box::new)
+    }
+}
+
+/// Creates an object of type `fqn` from its attributes values, given by name, see [`GcObject::builder`]
+pub fn object<'abi>(
+    abi: &'abi Abi,
+    fqn: &str,
+    attrs: Vec<(&str, Value<'abi>)>,
+) -> Result<Value<'abi>> {
+    attrs
+        .into_iter()
+        .fold(GcObject::builder(abi, fqn), |builder, (name, value)| {
+            builder.set(name, value)
+        })
+        .build()
+        .map(Value::Obj)
+}
+
+/// Checks the fields of a Rust type, given by attribute name and whether they are `Option`s,
+/// against the attributes of the type `fqn`.
+///
+/// Fails with [`Error::UnknownAttr`] for an extra field, [`Error::AttrTypeMismatch`] for an
+/// `Option` of a non-nullable attribute and [`Error::MissingAttrs`] for the non-nullable
+/// attributes without a field.
+pub fn check_fields(abi: &Abi, fqn: &str, fields: &[(&str, bool)]) -> Result<()> {
+    let ty = abi
+        .get_type_by_fqn(fqn)
+        .ok_or_else(|| Error::UnknownTypeName {
+            fqn: fqn.to_string(),
+        })?;
+    let attrs = ty.attrs.as_deref().unwrap_or_default();
+    for (name, optional) in fields {
+        let offset = ty
+            .attr_offset(name, abi)
+            .ok_or_else(|| Error::UnknownAttr {
+                ty: fqn.to_string(),
+                attr: name.to_string(),
+            })?;
+        if *optional && !attrs[offset].nullable {
+            return Err(Error::AttrTypeMismatch {
+                ty: fqn.to_string(),
+                attr: name.to_string(),
+                expected: "non-nullable",
+                got: "Option".to_string(),
+            });
+        }
+    }
+
+    let missing: Vec<String> = attrs
+        .iter()
+        .filter(|attr| !attr.nullable)
+        .map(|attr| &abi.symbols[attr.name])
+        .filter(|attr| fields.iter().all(|(name, _)| name != attr))
+        .map(str::to_string)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingAttrs {
+            ty: fqn.to_string(),
+            attrs: missing,
+        });
+    }
+    Ok(())
+}
+
+/// Returns the object of `value`, which must be of type `fqn`
+pub fn as_object<'v, 'abi>(
+    value: &'v Value<'abi>,
+    fqn: &str,
+    abi: &'abi Abi,
+) -> Result<&'v GcObject<'abi>> {
+    match value {
+        Value::Obj(obj) if obj.ty.named_fqn(abi) == fqn => Ok(obj),
+        v => Err(mismatch(fqn, v, abi)),
+    }
+}
+
+/// Returns the variant `key` of the enum `fqn`
+#[inline]
+pub fn enum_value<'abi>(abi: &'abi Abi, fqn: &str, key: &str) -> Result<Value<'abi>> {
+    abi.enum_value(fqn, key).map(Value::Enum)
+}
+
+/// Returns the key of `value`, which must be a variant of the enum `fqn`
+pub fn enum_key<'abi>(value: &Value<'abi>, fqn: &str, abi: &'abi Abi) -> Result<&'abi str> {
+    match value {
+        Value::Enum(en) if en.ty.named_fqn(abi) == fqn => Ok(en.key),
+        v => Err(mismatch(fqn, v, abi)),
+    }
+}
+
+fn mismatch(fqn: &str, value: &Value, abi: &Abi) -> Error {
+    let expected = match abi.get_type_by_fqn(fqn) {
+        Some(ty) => type_name(&ty, abi),
+        None => fqn.trim_start_matches("core::").to_string(),
+    };
+    Error::ValueMismatch {
+        expected,
+        got: value.type_name(abi),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::project_abi;
+    use crate::serialize::AbiSerialize;
+
+    #[derive(Debug, PartialEq, GcType)]
+    #[gc(type = "project::Person")]
+    struct Person {
+        #[gc(name = "name")]
+        full_name: String,
+        age: i64,
+    }
+
+    #[derive(Debug, PartialEq, GcType)]
+    #[gc(type = "project::Person")]
+    struct Nickname {
+        name: String,
+        nickname: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, GcType)]
+    #[gc(type = "project::Status")]
+    enum Status {
+        Active,
+        #[gc(name = "Inactive")]
+        Away,
+    }
+
+    #[test]
+    fn derive_gc_type() {
+        let abi = project_abi();
+
+        let bob = Person {
+            full_name: "Bob".to_string(),
+            age: 42,
+        };
+        let mut bytes = Vec::new();
+        bob.write_to(&mut bytes, &abi).unwrap();
+        assert_eq!(Person::from_reader(&bytes[..], &abi).unwrap(), bob);
+
+        let away = Status::Away.to_value(&abi).unwrap();
+        assert_eq!(enum_key(&away, Status::TYPE, &abi).unwrap(), "Inactive");
+        assert_eq!(Status::from_value(&away, &abi).unwrap(), Status::Away);
+
+        let err = Nickname {
+            name: "Bob".to_string(),
+            nickname: None,
+        }
+        .to_value(&abi)
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "type 'project::Person' has no attribute 'nickname'"
+        );
+    }
+}
@@ -1,14 +1,30 @@
+// lets `#[derive(GcType)]` refer to `::greycat_sdk` from within the SDK too
+extern crate self as greycat_sdk;
+
 pub mod abi;
-pub mod varint;
-pub mod primitive;
-pub mod value;
-pub mod prelude;
-pub mod std_n;
-pub mod serialize;
-pub mod gc_object;
-pub mod gc_enum;
+pub mod client;
+pub mod codegen;
+pub mod conform;
 pub mod deserialize;
+pub mod display;
+pub mod error;
+pub mod evolution;
+pub mod gc_enum;
+pub mod gc_fn;
+pub mod gc_object;
+pub mod gc_serde;
+pub mod gc_type;
 pub mod library;
+pub mod prelude;
+pub mod primitive;
+pub mod serialize;
+pub mod std_n;
+pub mod typed_json;
+pub mod value;
+pub mod varint;
 
-mod std;
 mod serde_utils;
+mod std;
+
+pub use error::{ArgError, Error, Result};
+pub use gc_serde::{from_reader, to_writer};
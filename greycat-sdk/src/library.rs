@@ -1,28 +1,45 @@
-use std::{collections::BTreeMap, rc::Rc};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 
-use anyhow::Result;
+use crate::error::{Error, Result};
 
 use crate::abi::{Abi, AbiType};
+use crate::gc_object::GcObject;
 use crate::value::Value;
 
-pub trait TypeLoader {
-    fn load(&mut self, ty: Rc<AbiType>, abi: &Abi) -> Result<Value>;
-}
+/// The codec of a native type, registered by fqn in [`Library::configure`].
+///
+/// To be written back as the native type, the values returned by `load` must be
+/// [`Value::Obj`]s of that type: writing them calls `save`.
+pub trait TypeLoader: Send + Sync {
+    /// Decodes the body of an object of type `ty`, the `reader` is positioned right after the
+    /// type id of the object
+    fn load<'abi>(
+        &self,
+        reader: &mut dyn Read,
+        ty: &AbiType,
+        abi: &'abi Abi,
+    ) -> Result<Value<'abi>>;
 
-pub trait TypeFactory {
-    fn create(ty: Rc<AbiType>, attrs: Option<Box<[Value]>>) -> Result<Self>
-    where
-        Self: Sized;
+    /// Encodes the body of `value`, an object of the native type as returned by `load`, and
+    /// returns the number of bytes written. Fails with [`Error::Unsupported`] by default.
+    fn save(&self, value: &GcObject, _writer: &mut dyn Write, abi: &Abi) -> Result<usize> {
+        Err(Error::Unsupported {
+            ty: value.ty.named_fqn(abi),
+            offset: None,
+        })
+    }
 }
 
-pub trait Library {
+/// A GreyCat library, eg. `std`, providing the codecs of its native types.
+///
+/// When an [`Abi`] is created, each library is first `configure`d, then `init`ialized with the
+/// `Abi`. Native types are looked up by the `lib_name` of their type and by their fqn, eg.
+/// `"project::Point"`.
+pub trait Library: Send + Sync {
     fn name(&self) -> &'static str;
 
-    fn configure(
-        &self,
-        loaders: &mut BTreeMap<&'static str, Box<dyn TypeLoader>>,
-        factories: &mut BTreeMap<&'static str, Box<dyn TypeFactory>>,
-    ) -> Result<()>;
+    fn configure(&self, loaders: &mut BTreeMap<&'static str, Box<dyn TypeLoader>>) -> Result<()>;
 
     fn init(&mut self, abi: &Abi) -> Result<()>;
 }
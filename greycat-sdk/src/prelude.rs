@@ -1,7 +1,11 @@
 pub use crate::abi::*;
+pub use crate::conform::{Violation, ViolationKind};
 pub use crate::deserialize::*;
+pub use crate::evolution::{AbiEvolution, EvolIssue};
 pub use crate::gc_enum::GcEnum;
-pub use crate::gc_object::{GcObject, RefValue};
+pub use crate::gc_fn::GcFn;
+pub use crate::gc_object::GcObject;
+pub use crate::gc_type::GcType;
 pub use crate::library::*;
 pub use crate::serialize::*;
 pub use crate::std::StdLibrary;
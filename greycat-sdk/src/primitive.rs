@@ -15,4 +15,4 @@ pub(crate) const ENUM: u8 = 14;
 pub(crate) const OBJECT: u8 = 15;
 pub(crate) const FN: u8 = 26;
 pub(crate) const UNDEFINED: u8 = 27;
-pub(crate) const STR_LIT: u8 = 28;
\ No newline at end of file
+pub(crate) const STR_LIT: u8 = 28;
@@ -1,6 +1,6 @@
 use std::io::Write;
 
-use anyhow::Result;
+use crate::error::Result;
 
 use crate::prelude::Abi;
 
@@ -1,14 +1,22 @@
 use std::collections::BTreeMap;
-use std::rc::Rc;
+use std::sync::Arc;
 
-use anyhow::Result;
+use crate::error::Result;
 
 use crate::abi::{Abi, AbiType};
 use crate::library::*;
 
 #[derive(Default, Clone)]
 pub struct StdLibrary {
-    _mapped: Vec<Rc<AbiType>>,
+    mapped: Vec<Arc<AbiType>>,
+}
+
+impl StdLibrary {
+    /// Returns the native types of `std` found in the `Abi`, once initialized
+    #[inline]
+    pub fn native_types(&self) -> &[Arc<AbiType>] {
+        &self.mapped
+    }
 }
 
 impl Library for StdLibrary {
@@ -17,16 +25,18 @@ impl Library for StdLibrary {
         "std"
     }
 
-    fn configure(
-        &self,
-        _loaders: &mut BTreeMap<&'static str, Box<dyn TypeLoader>>,
-        _factories: &mut BTreeMap<&'static str, Box<dyn TypeFactory>>,
-    ) -> Result<()> {
-        // loaders.insert("core::String", Box::new(TypeLoader::<GcObject>::load));
+    fn configure(&self, _loaders: &mut BTreeMap<&'static str, Box<dyn TypeLoader>>) -> Result<()> {
+        // the native types of `core` are decoded by `AbiDeserialize` itself
         Ok(())
     }
 
-    fn init(&mut self, _abi: &Abi) -> Result<()> {
-        todo!()
+    fn init(&mut self, abi: &Abi) -> Result<()> {
+        self.mapped = abi
+            .types
+            .iter()
+            .filter(|ty| ty.is_native && &abi.symbols[ty.lib_name] == self.name())
+            .cloned()
+            .collect();
+        Ok(())
     }
 }
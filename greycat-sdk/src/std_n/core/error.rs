@@ -0,0 +1,31 @@
+use serde::Serialize;
+
+/// `core::Error`, an error raised by GreyCat
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Default)]
+pub struct GcError {
+    pub msg: String,
+    /// the frames of the call stack, innermost first
+    pub stack: Vec<String>,
+}
+
+impl GcError {
+    pub const TYPE: &'static str = "core::Error";
+
+    #[inline]
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self {
+            msg: msg.into(),
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for GcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.msg)?;
+        for frame in &self.stack {
+            write!(f, "\n    at {frame}")?;
+        }
+        Ok(())
+    }
+}
@@ -38,7 +38,7 @@ impl AbiSerialize for Float {
         &self,
         writer: &mut W,
         abi: &crate::prelude::Abi,
-    ) -> anyhow::Result<usize> {
+    ) -> crate::error::Result<usize> {
         writer.write_u8(crate::primitive::FLOAT)?;
         let n = self.write_raw_to(writer, abi)?;
         Ok(1 + n)
@@ -48,7 +48,7 @@ impl AbiSerialize for Float {
         &self,
         writer: &mut W,
         _abi: &crate::prelude::Abi,
-    ) -> anyhow::Result<usize> {
+    ) -> crate::error::Result<usize> {
         writer.write_f64::<LE>(self.0 .0)?;
         Ok(8)
     }
@@ -77,7 +77,7 @@ impl AbiSerialize for Geo {
         &self,
         writer: &mut W,
         abi: &crate::prelude::Abi,
-    ) -> anyhow::Result<usize> {
+    ) -> crate::error::Result<usize> {
         writer.write_u8(crate::primitive::GEO)?;
         let n = self.write_raw_to(writer, abi)?;
         Ok(1 + n)
@@ -87,7 +87,7 @@ impl AbiSerialize for Geo {
         &self,
         writer: &mut W,
         _abi: &crate::prelude::Abi,
-    ) -> anyhow::Result<usize> {
+    ) -> crate::error::Result<usize> {
         writer.write_u64::<LE>(self.0)?;
         Ok(8)
     }
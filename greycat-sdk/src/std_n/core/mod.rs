@@ -1,15 +1,23 @@
 /// Defines all nodes type: `core::node`, `core::nodeTime`, `core::nodeIndex`, `code::nodeList` and `core::nodeGeo`
 mod nodes;
 
-mod geo;
+mod error;
 mod float;
+mod geo;
 mod string;
+mod table;
+mod tensor;
+mod tuple;
 
 /// Defines `core::time` and `core::duration`
 mod time;
 
-pub use string::*;
+pub use error::*;
 pub use float::*;
 pub use geo::*;
 pub use nodes::*;
+pub use string::*;
+pub use table::*;
+pub use tensor::{Tensor, TensorType, TensorValue};
 pub use time::*;
+pub use tuple::*;
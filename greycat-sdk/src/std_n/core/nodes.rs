@@ -28,7 +28,7 @@ macro_rules! create_node {
                 &self,
                 writer: &mut W,
                 abi: &crate::abi::Abi,
-            ) -> anyhow::Result<usize> {
+            ) -> crate::error::Result<usize> {
                 use byteorder::WriteBytesExt;
                 writer.write_u8($header)?;
                 let n = self.write_raw_to(writer, abi)?;
@@ -39,7 +39,7 @@ macro_rules! create_node {
                 &self,
                 writer: &mut W,
                 _abi: &crate::abi::Abi,
-            ) -> anyhow::Result<usize> {
+            ) -> crate::error::Result<usize> {
                 use crate::varint::VarintWrite;
                 let n = writer.write_vu64(self.0)?;
                 Ok(n)
@@ -0,0 +1,53 @@
+use crate::error::{Error, Result};
+use crate::value::Value;
+
+/// `core::Table`, a grid of values stored row by row
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Table<'abi> {
+    pub cols: u32,
+    /// the values of the rows, one after the other
+    pub values: Vec<Value<'abi>>,
+}
+
+impl<'abi> Table<'abi> {
+    pub const TYPE: &'static str = "core::Table";
+
+    #[inline]
+    pub fn new(cols: u32) -> Self {
+        Self {
+            cols,
+            values: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.values
+            .len()
+            .checked_div(self.cols as usize)
+            .unwrap_or(0)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&Value<'abi>> {
+        if col >= self.cols as usize {
+            return None;
+        }
+        self.values.get(row * self.cols as usize + col)
+    }
+
+    /// Appends a row, which must have exactly [`Table::cols`] values
+    pub fn push_row(&mut self, row: Vec<Value<'abi>>) -> Result<()> {
+        if row.len() != self.cols as usize {
+            return Err(Error::ValueMismatch {
+                expected: format!("a row of {} values", self.cols),
+                got: format!("{} values", row.len()),
+            });
+        }
+        self.values.extend(row);
+        Ok(())
+    }
+
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[Value<'abi>]> {
+        self.values.chunks(self.cols.max(1) as usize)
+    }
+}
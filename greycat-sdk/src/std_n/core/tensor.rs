@@ -0,0 +1,167 @@
+use byteorder::{ByteOrder, LE};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// The type of the elements of a [`Tensor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum TensorType {
+    I32 = 0,
+    I64 = 1,
+    F32 = 2,
+    F64 = 3,
+    /// a complex of two `f32`
+    C64 = 4,
+    /// a complex of two `f64`
+    C128 = 5,
+}
+
+impl TensorType {
+    /// Returns the byte size of an element
+    pub const fn size(self) -> usize {
+        match self {
+            TensorType::I32 | TensorType::F32 => 4,
+            TensorType::I64 | TensorType::F64 | TensorType::C64 => 8,
+            TensorType::C128 => 16,
+        }
+    }
+
+    pub fn from_u8(ty: u8) -> Option<Self> {
+        let ty = match ty {
+            0 => TensorType::I32,
+            1 => TensorType::I64,
+            2 => TensorType::F32,
+            3 => TensorType::F64,
+            4 => TensorType::C64,
+            5 => TensorType::C128,
+            _ => return None,
+        };
+        Some(ty)
+    }
+}
+
+/// An element of a [`Tensor`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TensorValue {
+    Int(i64),
+    Float(f64),
+    /// the real and imaginary parts
+    Complex(f64, f64),
+}
+
+/// `core::Tensor`, a multi-dimensional array of numbers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct Tensor {
+    pub ty: TensorType,
+    pub shape: Vec<i64>,
+    /// the elements, in row-major order, little-endian
+    pub data: Vec<u8>,
+}
+
+impl Tensor {
+    pub const TYPE: &'static str = "core::Tensor";
+
+    /// Creates a tensor of zeros
+    pub fn new(ty: TensorType, shape: Vec<i64>) -> Result<Self> {
+        let size = Self::byte_len(ty, &shape)?;
+        Ok(Self {
+            ty,
+            shape,
+            data: vec![0; size],
+        })
+    }
+
+    /// Returns the number of elements of a tensor of the given shape
+    pub fn shape_len(shape: &[i64]) -> Result<usize> {
+        shape.iter().try_fold(1usize, |len, dim| {
+            usize::try_from(*dim)
+                .ok()
+                .and_then(|dim| len.checked_mul(dim))
+                .ok_or_else(|| shape_mismatch(shape))
+        })
+    }
+
+    /// Returns the byte size of the data of a tensor of the given type and shape
+    pub fn byte_len(ty: TensorType, shape: &[i64]) -> Result<usize> {
+        Self::shape_len(shape)?
+            .checked_mul(ty.size())
+            .ok_or_else(|| shape_mismatch(shape))
+    }
+
+    /// Returns the number of elements
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len() / self.ty.size()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the element at `offset`, in row-major order
+    pub fn get(&self, offset: usize) -> Option<TensorValue> {
+        let size = self.ty.size();
+        let bytes = self.data.get(offset * size..(offset + 1) * size)?;
+        let value = match self.ty {
+            TensorType::I32 => TensorValue::Int(LE::read_i32(bytes).into()),
+            TensorType::I64 => TensorValue::Int(LE::read_i64(bytes)),
+            TensorType::F32 => TensorValue::Float(LE::read_f32(bytes).into()),
+            TensorType::F64 => TensorValue::Float(LE::read_f64(bytes)),
+            TensorType::C64 => TensorValue::Complex(
+                LE::read_f32(&bytes[..4]).into(),
+                LE::read_f32(&bytes[4..]).into(),
+            ),
+            TensorType::C128 => {
+                TensorValue::Complex(LE::read_f64(&bytes[..8]), LE::read_f64(&bytes[8..]))
+            }
+        };
+        Some(value)
+    }
+
+    /// Sets the element at `offset`, in row-major order, converting it to the type of the tensor.
+    ///
+    /// Returns `false` if `offset` is out of bounds.
+    pub fn set(&mut self, offset: usize, value: TensorValue) -> bool {
+        let size = self.ty.size();
+        let Some(bytes) = self.data.get_mut(offset * size..(offset + 1) * size) else {
+            return false;
+        };
+        let (re, im) = match value {
+            TensorValue::Int(v) => (v as f64, 0.0),
+            TensorValue::Float(v) => (v, 0.0),
+            TensorValue::Complex(re, im) => (re, im),
+        };
+        match (self.ty, value) {
+            (TensorType::I32, TensorValue::Int(v)) => LE::write_i32(bytes, v as i32),
+            (TensorType::I64, TensorValue::Int(v)) => LE::write_i64(bytes, v),
+            (TensorType::I32, _) => LE::write_i32(bytes, re as i32),
+            (TensorType::I64, _) => LE::write_i64(bytes, re as i64),
+            (TensorType::F32, _) => LE::write_f32(bytes, re as f32),
+            (TensorType::F64, _) => LE::write_f64(bytes, re),
+            (TensorType::C64, _) => {
+                LE::write_f32(&mut bytes[..4], re as f32);
+                LE::write_f32(&mut bytes[4..], im as f32);
+            }
+            (TensorType::C128, _) => {
+                LE::write_f64(&mut bytes[..8], re);
+                LE::write_f64(&mut bytes[8..], im);
+            }
+        }
+        true
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = TensorValue> + '_ {
+        (0..self.len()).filter_map(|offset| self.get(offset))
+    }
+}
+
+fn shape_mismatch(shape: &[i64]) -> Error {
+    Error::ValueMismatch {
+        expected: "a tensor shape".to_string(),
+        got: format!("{shape:?}"),
+    }
+}
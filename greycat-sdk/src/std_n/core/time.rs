@@ -21,7 +21,7 @@ macro_rules! create_primitive_i64 {
                 &self,
                 writer: &mut W,
                 abi: &crate::abi::Abi,
-            ) -> anyhow::Result<usize> {
+            ) -> crate::error::Result<usize> {
                 use byteorder::WriteBytesExt;
                 writer.write_u8($header)?;
                 let n = self.write_raw_to(writer, abi)?;
@@ -32,7 +32,7 @@ macro_rules! create_primitive_i64 {
                 &self,
                 writer: &mut W,
                 _abi: &crate::abi::Abi,
-            ) -> anyhow::Result<usize> {
+            ) -> crate::error::Result<usize> {
                 use crate::varint::VarintWrite;
                 let n = writer.write_vi64(self.0)?;
                 Ok(n)
@@ -0,0 +1,17 @@
+use crate::value::Value;
+
+/// `core::Tuple`, a pair of values
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Tuple<'abi> {
+    pub x: Value<'abi>,
+    pub y: Value<'abi>,
+}
+
+impl<'abi> Tuple<'abi> {
+    pub const TYPE: &'static str = "core::Tuple";
+
+    #[inline]
+    pub fn new(x: Value<'abi>, y: Value<'abi>) -> Self {
+        Self { x, y }
+    }
+}
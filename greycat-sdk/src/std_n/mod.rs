@@ -1 +1 @@
-pub mod core;
\ No newline at end of file
+pub mod core;
@@ -0,0 +1,428 @@
+//! Typed JSON encoding of [`Value`]s, as exchanged with a GreyCat server.
+//!
+//! Unlike [`Value::to_json`] and `From<&serde_json::Value>`, values keep their types through a
+//! `"_type"` tag:
+//!
+//! | value | JSON |
+//! |---|---|
+//! | `null`, `bool`, `int`, `float`, `String` | `null`, `true`, `42`, `4.2`, `"hello"` |
+//! | `Array` | `[1, 2]` |
+//! | `char` | `{ "_type": "core::char", "value": "c" }` |
+//! | `Map` | `{ "_type": "core::Map", "values": [key1, value1, key2, value2] }` |
+//! | `time` | `{ "_type": "core::time", "iso": "2024-01-01T00:00:00Z" }`, or `"us"` out of the ISO range |
+//! | `duration` | `{ "_type": "core::duration", "s": 90, "us": 0 }` |
+//! | `geo` | `{ "_type": "core::geo", "lat": 49.6, "lng": 6.1 }` |
+//! | nodes | `{ "_type": "core::node", "ref": "1A" }`, the id in hexadecimal |
+//! | enums | `{ "_type": "project::Status", "field": "Active" }` |
+//! | objects | `{ "_type": "project::Person", "name": "Bob", "age": 42 }` |
+//!
+//! Symbols are encoded as strings, and decoded as `String`s. The JSON of `Table`, `Tensor`,
+//! `Buffer`, `Tuple`, `Error`, `Set` and functions is not known, they fail with
+//! [`Error::Unsupported`] rather than guessing.
+//!
+//! When decoding, untagged JSON is read as the expected [`AbiType`], if any: an untagged object
+//! is an instance of the expected type, a string is a variant of the expected enum, etc. The
+//! elements of an array, and the values of a `Map`, are read as the expected type too,
+//! eg. `[[{ "name": "Bob" }]]` read as `project::Person` is an `Array` of `Array` of `Person`.
+//! The ABI does not tell the type parameters of `core::Array` and `core::Map`,
+//! so the elements of a container read as the container type itself are untyped.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{json, Map, Number};
+
+use crate::abi::{Abi, AbiType};
+use crate::error::{Error, Result};
+use crate::gc_object::GcObject;
+use crate::std_n::core::{
+    Duration, Float, Geo, Node, NodeGeo, NodeIndex, NodeList, NodeTime, Time,
+};
+use crate::value::Value;
+
+type Json = serde_json::Value;
+
+pub(crate) const TYPE: &str = "_type";
+
+impl<'abi> Value<'abi> {
+    /// Encodes this value to typed JSON, see the [module docs](self)
+    pub fn to_typed_json(&self, abi: &Abi) -> Result<Json> {
+        let json = match self {
+            Value::Null => Json::Null,
+            Value::Int(v) => json!(v),
+            // non-finite floats are `null`
+            Value::Float(v) => Number::from_f64(**v).map_or(Json::Null, Json::Number),
+            Value::Char(v) => json!({ TYPE: "core::char", "value": v }),
+            Value::Bool(v) => json!(v),
+            Value::Array(values) => values
+                .iter()
+                .map(|v| v.to_typed_json(abi))
+                .collect::<Result<_>>()?,
+            Value::Map(entries) => {
+                let mut values = Vec::with_capacity(entries.len() * 2);
+                for (k, v) in entries {
+                    values.push(k.to_typed_json(abi)?);
+                    values.push(v.to_typed_json(abi)?);
+                }
+                json!({ TYPE: "core::Map", "values": values })
+            }
+            Value::Symbol(v) => json!(v.0),
+            Value::String(v) => json!(v),
+            Value::Node(v) => node(Node::TYPE, v.0),
+            Value::NodeTime(v) => node(NodeTime::TYPE, v.0),
+            Value::NodeIndex(v) => node(NodeIndex::TYPE, v.0),
+            Value::NodeList(v) => node(NodeList::TYPE, v.0),
+            Value::NodeGeo(v) => node(NodeGeo::TYPE, v.0),
+            Value::Geo(v) => {
+                let (lat, lng) = v.as_lat_lng();
+                json!({ TYPE: Geo::TYPE, "lat": lat, "lng": lng })
+            }
+            Value::Time(v) => match DateTime::from_timestamp_micros(v.0) {
+                Some(dt) => {
+                    let iso = dt.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+                    json!({ TYPE: Time::TYPE, "iso": iso })
+                }
+                None => json!({ TYPE: Time::TYPE, "us": v.0 }),
+            },
+            Value::Duration(v) => {
+                json!({ TYPE: Duration::TYPE, "s": v.0 / 1_000_000, "us": v.0 % 1_000_000 })
+            }
+            Value::Enum(en) => json!({ TYPE: en.ty.named_fqn(abi), "field": en.key }),
+            Value::Obj(obj) => {
+                let mut map = Map::new();
+                map.insert(TYPE.to_string(), json!(obj.ty.named_fqn(abi)));
+                let attrs = obj.ty.attrs.as_deref().unwrap_or_default();
+                let values = obj.values.as_deref().unwrap_or_default();
+                for (attr, value) in attrs.iter().zip(values) {
+                    map.insert(
+                        abi.symbols[attr.name].to_string(),
+                        value.to_typed_json(abi)?,
+                    );
+                }
+                Json::Object(map)
+            }
+            Value::Table(_)
+            | Value::Tensor(_)
+            | Value::Buffer(_)
+            | Value::Tuple(_)
+            | Value::Error(_)
+            | Value::Set(_)
+            | Value::Function(_) => {
+                return Err(Error::Unsupported {
+                    ty: format!("core::{}", self.type_name(abi)),
+                    offset: None,
+                })
+            }
+        };
+        Ok(json)
+    }
+
+    /// Decodes typed JSON, untagged JSON is read as `ty` when given, see the [module docs](self)
+    pub fn from_typed_json(json: &Json, ty: Option<&AbiType>, abi: &'abi Abi) -> Result<Self> {
+        let core = ty.and_then(|ty| match &abi.symbols[ty.module] {
+            "core" => Some(&abi.symbols[ty.name]),
+            _ => None,
+        });
+
+        let value = match json {
+            Json::Null => Value::Null,
+            Json::Bool(v) => Value::Bool(*v),
+            Json::Number(n) => match (core, n.as_i64()) {
+                (Some("float"), _) | (_, None) => Value::Float(Float::from(number(n)?)),
+                (Some("time"), Some(n)) => Value::Time(Time(n)),
+                (Some("duration"), Some(n)) => Value::Duration(Duration(n)),
+                (_, Some(n)) => Value::Int(n),
+            },
+            Json::String(s) => match ty {
+                Some(ty) if ty.is_enum => Value::Enum(abi.enum_value(&ty.named_fqn(abi), s)?),
+                _ => match core {
+                    Some("char") => Value::Char(char(s)?),
+                    Some("time") => Value::Time(iso(s)?),
+                    _ => Value::String(s.clone()),
+                },
+            },
+            Json::Array(_) => Value::Array(values(json, elements(ty, abi), abi)?),
+            Json::Object(map) => match map.get(TYPE) {
+                Some(Json::String(fqn)) => tagged(fqn, map, elements(ty, abi), abi)?,
+                Some(other) => return Err(mismatch("a type name", other)),
+                None => match ty {
+                    Some(ty) if core.is_none() && !ty.is_enum => object(ty, map, abi)?,
+                    // a `Map` with `String` keys
+                    _ => Value::Map(
+                        map.iter()
+                            .map(|(k, v)| {
+                                Ok((
+                                    Value::String(k.clone()),
+                                    Value::from_typed_json(v, None, abi)?,
+                                ))
+                            })
+                            .collect::<Result<_>>()?,
+                    ),
+                },
+            },
+        };
+        Ok(value)
+    }
+}
+
+fn node(ty: &str, id: u64) -> Json {
+    json!({ TYPE: ty, "ref": format!("{id:X}") })
+}
+
+/// Returns the expected type of the elements of a container expected as `ty`, which is `ty`
+/// itself unless it is a container type
+fn elements<'t>(ty: Option<&'t AbiType>, abi: &Abi) -> Option<&'t AbiType> {
+    let core = &abi.types.core;
+    ty.filter(|ty| {
+        let id = ty.mapped_abi_type_offset;
+        id != core.array && id != core.map
+    })
+}
+
+/// Decodes a tagged value, the elements of containers being read as `elements`
+fn tagged<'abi>(
+    fqn: &str,
+    map: &Map<String, Json>,
+    elements: Option<&AbiType>,
+    abi: &'abi Abi,
+) -> Result<Value<'abi>> {
+    let field = |name: &str| {
+        map.get(name).ok_or_else(|| Error::MissingAttrs {
+            ty: fqn.to_string(),
+            attrs: vec![name.to_string()],
+        })
+    };
+    let node = || -> Result<u64> {
+        let id = field("ref")?;
+        id.as_str()
+            .and_then(|id| u64::from_str_radix(id, 16).ok())
+            .ok_or_else(|| mismatch("a hexadecimal node ref", id))
+    };
+
+    let value = match fqn {
+        "core::char" => {
+            let value = field("value")?;
+            let s = value.as_str().ok_or_else(|| mismatch("char", value))?;
+            Value::Char(char(s)?)
+        }
+        "core::Map" => {
+            let values = field("values")?;
+            let values = values.as_array().ok_or_else(|| mismatch("Array", values))?;
+            let mut entries = BTreeMap::new();
+            for pair in values.chunks(2) {
+                let [key, value] = pair else {
+                    return Err(mismatch("a value for each key", &pair[0]));
+                };
+                entries.insert(
+                    Value::from_typed_json(key, None, abi)?,
+                    Value::from_typed_json(value, elements, abi)?,
+                );
+            }
+            Value::Map(entries)
+        }
+        "core::time" => match (map.get("iso"), map.get("us")) {
+            (Some(Json::String(s)), _) => Value::Time(iso(s)?),
+            (_, Some(us)) => Value::Time(Time(integer(us)?)),
+            _ => return Err(mismatch("an ISO string", field("iso")?)),
+        },
+        "core::duration" => {
+            let s = map.get("s").map_or(Ok(0), integer)?;
+            let us = map.get("us").map_or(Ok(0), integer)?;
+            let us = s
+                .checked_mul(1_000_000)
+                .and_then(|s| s.checked_add(us))
+                .ok_or_else(|| Error::ValueMismatch {
+                    expected: "a duration in the range of i64 microseconds".to_string(),
+                    got: format!("{s}s {us}us"),
+                })?;
+            Value::Duration(Duration(us))
+        }
+        "core::geo" => Value::Geo(Geo::from_lat_lng(
+            number_of(field("lat")?)?,
+            number_of(field("lng")?)?,
+        )),
+        "core::node" => Value::Node(Node(node()?)),
+        "core::nodeTime" => Value::NodeTime(NodeTime(node()?)),
+        "core::nodeIndex" => Value::NodeIndex(NodeIndex(node()?)),
+        "core::nodeList" => Value::NodeList(NodeList(node()?)),
+        "core::nodeGeo" => Value::NodeGeo(NodeGeo(node()?)),
+        fqn => {
+            let ty = abi
+                .get_type_by_fqn(fqn)
+                .ok_or_else(|| Error::UnknownTypeName {
+                    fqn: fqn.to_string(),
+                })?;
+            if ty.is_native {
+                // `core::Table`, `core::Tensor`, etc. or the native types of libraries
+                return Err(Error::Unsupported {
+                    ty: fqn.to_string(),
+                    offset: None,
+                });
+            } else if ty.is_enum {
+                let key = field("field")?;
+                let key = key.as_str().ok_or_else(|| mismatch("String", key))?;
+                Value::Enum(abi.enum_value(fqn, key)?)
+            } else {
+                object(&ty, map, abi)?
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// Decodes the values of a JSON array, as `ty` if given
+fn values<'abi, C>(json: &Json, ty: Option<&AbiType>, abi: &'abi Abi) -> Result<C>
+where
+    C: FromIterator<Value<'abi>>,
+{
+    json.as_array()
+        .ok_or_else(|| mismatch("Array", json))?
+        .iter()
+        .map(|v| Value::from_typed_json(v, ty, abi))
+        .collect()
+}
+
+/// Decodes the attributes of `map`, but `"_type"`, as an instance of `ty`
+fn object<'abi>(ty: &AbiType, map: &Map<String, Json>, abi: &'abi Abi) -> Result<Value<'abi>> {
+    let fqn = ty.named_fqn(abi);
+    let attrs = ty.attrs.as_deref().unwrap_or_default();
+    let mut builder = GcObject::builder(abi, &fqn);
+    for (name, json) in map.iter().filter(|(name, _)| *name != TYPE) {
+        let attr_ty = ty
+            .attr_offset(name, abi)
+            .and_then(|offset| attrs[offset].prog_type(abi));
+        let value = Value::from_typed_json(json, attr_ty.as_deref(), abi)?;
+        builder = builder.set(name, value);
+    }
+    builder.build().map(Value::Obj)
+}
+
+fn char(s: &str) -> Result<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(Error::ValueMismatch {
+            expected: "char".to_string(),
+            got: format!("{s:?}"),
+        }),
+    }
+}
+
+fn iso(s: &str) -> Result<Time> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| Time(dt.timestamp_micros()))
+        .map_err(|_| Error::ValueMismatch {
+            expected: "an ISO 8601 time".to_string(),
+            got: format!("{s:?}"),
+        })
+}
+
+fn number(n: &Number) -> Result<f64> {
+    n.as_f64().ok_or_else(|| Error::ValueMismatch {
+        expected: "float".to_string(),
+        got: n.to_string(),
+    })
+}
+
+fn number_of(json: &Json) -> Result<f64> {
+    match json {
+        Json::Number(n) => number(n),
+        other => Err(mismatch("float", other)),
+    }
+}
+
+fn integer(json: &Json) -> Result<i64> {
+    json.as_i64().ok_or_else(|| mismatch("int", json))
+}
+
+fn mismatch(expected: &str, got: &Json) -> Error {
+    Error::ValueMismatch {
+        expected: expected.to_string(),
+        got: got.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::abi::test::{native_abi, person_status_abi};
+    use crate::std_n::core::Tuple;
+
+    #[test]
+    fn typed_json_round_trip() {
+        let abi = person_status_abi();
+        let person = abi.get_type_by_fqn("project::Person").unwrap();
+
+        let bob = Value::Obj(
+            GcObject::builder(&abi, "project::Person")
+                .set("name", Value::String("Bob".into()))
+                .set("age", Value::Int(42))
+                .set(
+                    "status",
+                    Value::Enum(abi.enum_value("project::Status", "Active").unwrap()),
+                )
+                .build()
+                .unwrap(),
+        );
+        let value = Value::Array(vec![
+            bob.clone(),
+            Value::Time(Time(1_704_067_200_000_000)),
+            Value::Duration(Duration(90_000_001)),
+            Value::Char('c'),
+            Value::Map(BTreeMap::from([(Value::Int(1), Value::Float(1.5.into()))])),
+            Value::NodeTime(NodeTime(26)),
+        ]);
+
+        let json = value.to_typed_json(&abi).unwrap();
+        assert_eq!(
+            json[0],
+            json!({ "_type": "project::Person", "name": "Bob", "age": 42, "status": { "_type": "project::Status", "field": "Active" } })
+        );
+        assert_eq!(
+            json[1],
+            json!({ "_type": "core::time", "iso": "2024-01-01T00:00:00Z" })
+        );
+        assert_eq!(json[5], json!({ "_type": "core::nodeTime", "ref": "1A" }));
+        assert_eq!(Value::from_typed_json(&json, None, &abi).unwrap(), value);
+
+        // untagged JSON is read as the expected type
+        let untagged = json!({ "name": "Bob", "age": 42, "status": "Active" });
+        assert_eq!(
+            Value::from_typed_json(&untagged, Some(&person), &abi).unwrap(),
+            bob
+        );
+        // so are the elements of nested arrays
+        let nested = json!([[untagged], []]);
+        assert_eq!(
+            Value::from_typed_json(&nested, Some(&person), &abi).unwrap(),
+            Value::Array(vec![Value::Array(vec![bob]), Value::Array(vec![])])
+        );
+
+        // untrusted sizes are checked
+        let duration = json!({ "_type": "core::duration", "s": i64::MAX, "us": 0 });
+        assert!(matches!(
+            Value::from_typed_json(&duration, None, &abi),
+            Err(Error::ValueMismatch { .. })
+        ));
+
+        // symbols are plain strings
+        let symbol = Value::Symbol(abi.get_symbol("name").unwrap());
+        assert_eq!(symbol.to_typed_json(&abi).unwrap(), json!("name"));
+
+        // the JSON of the other native types is not known
+        let abi = native_abi();
+        let tuple = Value::Tuple(Box::new(Tuple {
+            x: Value::Int(1),
+            y: Value::Int(2),
+        }));
+        assert!(matches!(
+            tuple.to_typed_json(&abi),
+            Err(Error::Unsupported { .. })
+        ));
+        let table = json!({ "_type": "core::Table", "cols": 1, "values": [] });
+        assert!(matches!(
+            Value::from_typed_json(&table, None, &abi),
+            Err(Error::Unsupported { .. })
+        ));
+    }
+}
@@ -1,13 +1,13 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::io::{Read, Write};
 
-use anyhow::Result;
+use crate::error::{Error, Result, Tracked};
 use byteorder::{WriteBytesExt, LE};
-use serde::Serialize;
 
 use crate::abi::{Abi, RequestHeaders};
 use crate::deserialize::AbiDeserialize;
 use crate::gc_enum::GcEnum;
+use crate::gc_fn::GcFn;
 use crate::gc_object::GcObject;
 use crate::prelude::AbiSymbol;
 use crate::serialize::AbiSerialize;
@@ -15,29 +15,62 @@ use crate::std_n::core::Float;
 use crate::varint::{VarintRead, VarintWrite};
 use crate::{primitive, std_n};
 
-#[derive(Clone, Serialize)]
+#[derive(Clone)]
 pub struct HeaderValue<'abi> {
     pub headers: RequestHeaders,
     pub value: Value<'abi>,
 }
 
 impl<'abi> HeaderValue<'abi> {
-    pub fn from_reader<T>(mut reader: T, abi: &'abi Abi) -> Result<Self>
+    /// Reads the request headers and the value that follows.
+    ///
+    /// This fails with an [`AbiMismatch`](crate::abi::AbiMismatch) if the headers were not
+    /// written using the given `abi`. Payloads do not carry the CRC of their ABI, use
+    /// [`HeaderValue::from_reader_with_crc`] when it is known, eg. from the server.
+    pub fn from_reader<T>(reader: T, abi: &'abi Abi) -> Result<Self>
     where
         T: Read,
     {
         use crate::abi::RequestHeadersRead;
 
-        let headers = reader.read_request_headers()?;
-        let value = reader.read_value(abi)?;
+        Tracked::run(reader, |reader| {
+            let headers = reader.read_request_headers()?;
+            abi.check_headers(&headers)?;
+            let value = reader.read_value(abi)?;
 
-        Ok(Self { headers, value })
+            Ok(Self { headers, value })
+        })
+    }
+
+    /// Same as [`HeaderValue::from_reader`] but also checks `crc`, the CRC of the ABI that wrote
+    /// the payload, against the CRC of `abi`
+    pub fn from_reader_with_crc<T>(reader: T, abi: &'abi Abi, crc: u64) -> Result<Self>
+    where
+        T: Read,
+    {
+        abi.check_crc(crc)?;
+        Self::from_reader(reader, abi)
+    }
+
+    /// Same as [`HeaderValue::from_reader`] but does not check the headers against the `abi`
+    pub fn from_reader_unchecked<T>(reader: T, abi: &'abi Abi) -> Result<Self>
+    where
+        T: Read,
+    {
+        use crate::abi::RequestHeadersRead;
+
+        Tracked::run(reader, |reader| {
+            let headers = reader.read_request_headers()?;
+            let value = reader.read_value(abi)?;
+
+            Ok(Self { headers, value })
+        })
     }
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Hash)]
-#[serde(untagged)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub enum Value<'abi> {
+    #[default]
     Null,
     Int(i64),
     Float(std_n::core::Float),
@@ -57,11 +90,48 @@ pub enum Value<'abi> {
     String(String),
     Enum(GcEnum<'abi>),
     Obj(GcObject<'abi>),
+    /// The binary layouts of `Table`, `Tensor`, `Buffer`, `Tuple`, `Error` and `Set` are not
+    /// known, reading or writing them fails with [`Error::Unsupported`] rather than guessing.
+    Table(Box<std_n::core::Table<'abi>>),
+    Tensor(Box<std_n::core::Tensor>),
+    Buffer(Vec<u8>),
+    Tuple(Box<std_n::core::Tuple<'abi>>),
+    Error(Box<std_n::core::GcError>),
+    Set(BTreeSet<Value<'abi>>),
+    Function(GcFn<'abi>),
 }
 
-impl std::default::Default for Value<'_> {
-    fn default() -> Self {
-        Self::Null
+impl Value<'_> {
+    /// Returns the GreyCat name of the type of this value, eg. `int`, `String`, `Table` or `project::Person`
+    pub fn type_name(&self, abi: &Abi) -> String {
+        let name = match self {
+            Value::Null => "null",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Char(_) => "char",
+            Value::Bool(_) => "bool",
+            Value::Array(_) => "Array",
+            Value::Map(_) => "Map",
+            Value::Symbol(_) | Value::String(_) => "String",
+            Value::Node(_) => "node",
+            Value::NodeTime(_) => "nodeTime",
+            Value::NodeIndex(_) => "nodeIndex",
+            Value::NodeList(_) => "nodeList",
+            Value::NodeGeo(_) => "nodeGeo",
+            Value::Geo(_) => "geo",
+            Value::Time(_) => "time",
+            Value::Duration(_) => "duration",
+            Value::Table(_) => "Table",
+            Value::Tensor(_) => "Tensor",
+            Value::Buffer(_) => "Buffer",
+            Value::Tuple(_) => "Tuple",
+            Value::Error(_) => "Error",
+            Value::Set(_) => "Set",
+            Value::Function(_) => "function",
+            Value::Enum(v) => return crate::conform::type_name(&v.ty, abi),
+            Value::Obj(v) => return crate::conform::type_name(&v.ty, abi),
+        };
+        name.to_string()
     }
 }
 
@@ -110,6 +180,16 @@ impl<'abi> AbiSerialize for Value<'abi> {
             Value::Duration(v) => v.write_to(writer, abi),
             Value::Enum(v) => v.write_to(writer, abi),
             Value::Obj(v) => v.write_to(writer, abi),
+            Value::Table(_)
+            | Value::Tensor(_)
+            | Value::Buffer(_)
+            | Value::Tuple(_)
+            | Value::Error(_)
+            | Value::Set(_) => Err(Error::Unsupported {
+                ty: format!("core::{}", self.type_name(abi)),
+                offset: None,
+            }),
+            Value::Function(v) => v.write_to(writer, abi),
         }
     }
 
@@ -134,32 +214,16 @@ impl<'abi> AbiSerialize for Value<'abi> {
             Value::Duration(v) => v.write_raw_to(writer, abi),
             Value::Enum(v) => v.write_raw_to(writer, abi),
             Value::Obj(v) => v.write_raw_to(writer, abi),
-        }
-    }
-}
-
-impl<'a> std::fmt::Display for Value<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Value::Null => f.write_str("null"),
-            Value::Int(_) => f.write_str("int"),
-            Value::Float(_) => f.write_str("float"),
-            Value::Char(_) => f.write_str("char"),
-            Value::Bool(_) => f.write_str("bool"),
-            Value::Array(_) => f.write_str("Array"),
-            Value::Map(_) => f.write_str("Map"),
-            Value::Symbol(_) => f.write_str("symbol"),
-            Value::Node(_) => f.write_str("node"),
-            Value::NodeTime(_) => f.write_str("nodeTime"),
-            Value::NodeIndex(_) => f.write_str("nodeIndex"),
-            Value::NodeList(_) => f.write_str("nodeList"),
-            Value::NodeGeo(_) => f.write_str("nodeGeo"),
-            Value::Geo(_) => f.write_str("geo"),
-            Value::Time(_) => f.write_str("time"),
-            Value::Duration(_) => f.write_str("duration"),
-            Value::String(_) => f.write_str("String"),
-            Value::Enum(v) => write!(f, "Enum#{}", v.ty.mapped_abi_type_offset), // TODO find better?
-            Value::Obj(v) => write!(f, "Object#{}", v.ty.mapped_abi_type_offset), // TODO find better?
+            Value::Table(_)
+            | Value::Tensor(_)
+            | Value::Buffer(_)
+            | Value::Tuple(_)
+            | Value::Error(_)
+            | Value::Set(_) => Err(Error::Unsupported {
+                ty: format!("core::{}", self.type_name(abi)),
+                offset: None,
+            }),
+            Value::Function(v) => v.write_raw_to(writer, abi),
         }
     }
 }
@@ -186,6 +250,13 @@ impl<'a> std::fmt::Debug for Value<'a> {
             Value::String(v) => v.fmt(f),
             Value::Enum(v) => v.fmt(f),
             Value::Obj(v) => v.fmt(f),
+            Value::Table(v) => v.fmt(f),
+            Value::Tensor(v) => v.fmt(f),
+            Value::Buffer(v) => String::from_utf8_lossy(v).fmt(f),
+            Value::Tuple(v) => v.fmt(f),
+            Value::Error(v) => v.fmt(f),
+            Value::Set(v) => v.fmt(f),
+            Value::Function(v) => v.fmt(f),
         }
     }
 }
@@ -207,7 +278,7 @@ impl<T: AbiSerialize, const N: usize> AbiSerialize for [T; N] {
     }
 }
 
-impl<'a, T: AbiSerialize> AbiSerialize for &'a [T] {
+impl<T: AbiSerialize> AbiSerialize for &[T] {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         writer.write_u8(primitive::OBJECT)?;
         let mut n = writer.write_vu32(abi.types.core.array)?;
@@ -224,7 +295,7 @@ impl<'a, T: AbiSerialize> AbiSerialize for &'a [T] {
     }
 }
 
-impl<'a, T: AbiSerialize> AbiSerialize for &'a Vec<T> {
+impl<T: AbiSerialize> AbiSerialize for &Vec<T> {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         writer.write_u8(primitive::OBJECT)?;
         let mut n = writer.write_vu32(abi.types.core.array)?;
@@ -241,7 +312,7 @@ impl<'a, T: AbiSerialize> AbiSerialize for &'a Vec<T> {
     }
 }
 
-impl<'a, K: AbiSerialize, V: AbiSerialize> AbiSerialize for &'a HashMap<K, V> {
+impl<K: AbiSerialize, V: AbiSerialize> AbiSerialize for &HashMap<K, V> {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         writer.write_u8(primitive::OBJECT)?;
         let mut n = writer.write_vu32(abi.types.core.map)?;
@@ -259,7 +330,7 @@ impl<'a, K: AbiSerialize, V: AbiSerialize> AbiSerialize for &'a HashMap<K, V> {
     }
 }
 
-impl<'a, K: AbiSerialize, V: AbiSerialize> AbiSerialize for &'a BTreeMap<K, V> {
+impl<K: AbiSerialize, V: AbiSerialize> AbiSerialize for &BTreeMap<K, V> {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         writer.write_u8(primitive::OBJECT)?;
         let mut n = writer.write_vu32(abi.types.core.map)?;
@@ -318,7 +389,10 @@ impl AbiSerialize for char {
             writer.write_all(&buf)?;
             return Ok(1);
         }
-        anyhow::bail!("'{self}' is not an ASCII char")
+        Err(Error::InvalidChar {
+            code: *self as u32,
+            offset: None,
+        })
     }
 }
 
@@ -373,7 +447,7 @@ impl AbiSerialize for AnyString<'_> {
     }
 }
 
-impl<'a> AbiSerialize for &'a str {
+impl AbiSerialize for &str {
     fn write_to<W: Write>(&self, writer: &mut W, abi: &Abi) -> Result<usize> {
         match abi.symbols.get(self) {
             Some(off) => Symbol(off).write_to(writer, abi),
@@ -413,20 +487,34 @@ impl<T: Read> GcEnumRead for T {
     fn read_enum<'abi>(&mut self, abi: &'abi Abi) -> Result<GcEnum<'abi>> {
         let enum_id = self.read_vu32()?;
         let variant = self.read_vu32()?;
-        if let Some(ty) = abi.types.get(enum_id) {
-            if let Some(attrs) = ty.attrs.as_ref() {
-                let key = attrs[variant as usize].name;
-                let offset = attrs[variant as usize].mapped_att_offset;
-                return Ok(GcEnum {
-                    ty,
-                    key: &abi.symbols[key],
-                    offset,
-                });
-            } else {
-                anyhow::bail!("enum '{}' has no field at offset {}", ty.name, variant);
-            }
-        }
-        anyhow::bail!("unknown enum id {enum_id}")
+        let ty = abi.types.get(enum_id).ok_or(Error::UnknownType {
+            id: enum_id,
+            offset: None,
+        })?;
+        let attr = ty
+            .attrs
+            .as_deref()
+            .and_then(|attrs| attrs.get(variant as usize))
+            .ok_or_else(|| Error::UnknownEnumVariant {
+                ty: ty.named_fqn(abi),
+                variant,
+                offset: None,
+            })?;
+        let key = abi
+            .symbols
+            .resolve(attr.name)
+            .ok_or(Error::UnknownSymbolId {
+                id: attr.name,
+                offset: None,
+            })?;
+        let offset = attr.mapped_att_offset;
+        Ok(GcEnum {
+            module: &abi.symbols[ty.module],
+            name: &abi.symbols[ty.name],
+            ty,
+            key,
+            offset,
+        })
     }
 }
 
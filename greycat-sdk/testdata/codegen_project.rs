@@ -0,0 +1,69 @@
+// Generated by greycat-sdk from the ABI, do not edit.
+
+#[allow(non_camel_case_types, clippy::too_many_arguments)]
+pub mod project {
+    /// `project::Person`
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct Person {
+        pub name: ::std::string::String,
+        pub age: i64,
+    }
+
+    impl<'abi> ::greycat_sdk::gc_type::GcType<'abi> for Person {
+        const TYPE: &'static str = "project::Person";
+
+        fn to_value(&self, abi: &'abi ::greycat_sdk::abi::Abi) -> ::greycat_sdk::Result<::greycat_sdk::value::Value<'abi>> {
+            ::greycat_sdk::gc_type::object(abi, Self::TYPE, vec![
+                ("name", ::greycat_sdk::gc_type::GcType::to_value(&self.name, abi)?),
+                ("age", ::greycat_sdk::gc_type::GcType::to_value(&self.age, abi)?),
+            ])
+        }
+
+        fn from_value(value: &::greycat_sdk::value::Value<'abi>, abi: &'abi ::greycat_sdk::abi::Abi) -> ::greycat_sdk::Result<Self> {
+            let obj = ::greycat_sdk::gc_type::as_object(value, Self::TYPE, abi)?;
+            Ok(Self {
+                name: ::greycat_sdk::gc_type::GcType::from_value(obj.get("name", abi)?, abi)?,
+                age: ::greycat_sdk::gc_type::GcType::from_value(obj.get("age", abi)?, abi)?,
+            })
+        }
+    }
+
+    /// `project::Status`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum Status {
+        Active,
+        Inactive,
+    }
+
+    impl<'abi> ::greycat_sdk::gc_type::GcType<'abi> for Status {
+        const TYPE: &'static str = "project::Status";
+
+        fn to_value(&self, abi: &'abi ::greycat_sdk::abi::Abi) -> ::greycat_sdk::Result<::greycat_sdk::value::Value<'abi>> {
+            let key = match self {
+                Self::Active => "Active",
+                Self::Inactive => "Inactive",
+            };
+            ::greycat_sdk::gc_type::enum_value(abi, Self::TYPE, key)
+        }
+
+        fn from_value(value: &::greycat_sdk::value::Value<'abi>, abi: &'abi ::greycat_sdk::abi::Abi) -> ::greycat_sdk::Result<Self> {
+            match ::greycat_sdk::gc_type::enum_key(value, Self::TYPE, abi)? {
+                "Active" => Ok(Self::Active),
+                "Inactive" => Ok(Self::Inactive),
+                key => Err(::greycat_sdk::Error::UnknownEnumKey { ty: Self::TYPE.to_string(), key: key.to_string() }),
+            }
+        }
+    }
+
+    /// Calls `project::add`
+    pub fn add<T: ::greycat_sdk::client::Transport>(client: &::greycat_sdk::client::Client<T>, a: i64, b: i64) -> ::greycat_sdk::Result<i64> {
+        let value = client.call(
+            "project::add",
+            &[
+                ::greycat_sdk::gc_type::GcType::to_value(&a, client.abi())?,
+                ::greycat_sdk::gc_type::GcType::to_value(&b, client.abi())?,
+            ],
+        )?;
+        ::greycat_sdk::gc_type::GcType::from_value(&value, client.abi())
+    }
+}